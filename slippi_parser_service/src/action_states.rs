@@ -0,0 +1,130 @@
+//! Melee action-state IDs as reported in Slippi pre/post frame data.
+//!
+//! Only the common (character-independent) states are listed here; IDs from
+//! `SPECIAL_START` upwards are character-specific.
+
+// The table mirrors the game's state list, so not every entry is referenced.
+#![allow(dead_code)]
+
+// Movement
+pub const WAIT: u16 = 0x0E;
+pub const WALK_SLOW: u16 = 0x0F;
+pub const TURN: u16 = 0x12;
+pub const DASH: u16 = 0x14;
+pub const RUN: u16 = 0x15;
+pub const RUN_BRAKE: u16 = 0x17;
+pub const KNEE_BEND: u16 = 0x18;
+pub const JUMP_F: u16 = 0x19;
+pub const JUMP_B: u16 = 0x1A;
+pub const JUMP_AERIAL_F: u16 = 0x1B;
+pub const JUMP_AERIAL_B: u16 = 0x1C;
+pub const FALL: u16 = 0x1D;
+pub const FALL_AERIAL_B: u16 = 0x22;
+pub const DAMAGE_FALL: u16 = 0x26;
+pub const SQUAT: u16 = 0x27;
+pub const SQUAT_RV: u16 = 0x29;
+pub const LANDING_FALL_SPECIAL: u16 = 0x2B;
+
+// Ground attacks
+pub const ATTACK_11: u16 = 0x2C;
+pub const ATTACK_12: u16 = 0x2D;
+pub const ATTACK_13: u16 = 0x2E;
+pub const ATTACK_100_START: u16 = 0x2F;
+pub const ATTACK_100_LOOP: u16 = 0x30;
+pub const ATTACK_100_END: u16 = 0x31;
+pub const ATTACK_DASH: u16 = 0x32;
+pub const ATTACK_S3_HI: u16 = 0x33;
+pub const ATTACK_S3_HI_S: u16 = 0x34;
+pub const ATTACK_S3_S: u16 = 0x35;
+pub const ATTACK_S3_LW_S: u16 = 0x36;
+pub const ATTACK_S3_LW: u16 = 0x37;
+pub const ATTACK_HI3: u16 = 0x38;
+pub const ATTACK_LW3: u16 = 0x39;
+pub const ATTACK_S4_HI: u16 = 0x3A;
+pub const ATTACK_S4_HI_S: u16 = 0x3B;
+pub const ATTACK_S4_S: u16 = 0x3C;
+pub const ATTACK_S4_LW_S: u16 = 0x3D;
+pub const ATTACK_S4_LW: u16 = 0x3E;
+pub const ATTACK_HI4: u16 = 0x3F;
+pub const ATTACK_LW4: u16 = 0x40;
+
+// Aerial attacks
+pub const ATTACK_AIR_N: u16 = 0x41;
+pub const ATTACK_AIR_F: u16 = 0x42;
+pub const ATTACK_AIR_B: u16 = 0x43;
+pub const ATTACK_AIR_HI: u16 = 0x44;
+pub const ATTACK_AIR_LW: u16 = 0x45;
+
+// Aerial landing lag
+pub const LANDING_AIR_N: u16 = 0x46;
+pub const LANDING_AIR_F: u16 = 0x47;
+pub const LANDING_AIR_B: u16 = 0x48;
+pub const LANDING_AIR_HI: u16 = 0x49;
+pub const LANDING_AIR_LW: u16 = 0x4A;
+
+// Hitstun
+pub const DAMAGE_HI_1: u16 = 0x4B;
+pub const DAMAGE_FLY_ROLL: u16 = 0x5B;
+
+// Shield
+pub const GUARD_ON: u16 = 0xB2;
+pub const GUARD: u16 = 0xB3;
+pub const GUARD_OFF: u16 = 0xB4;
+pub const GUARD_SET_OFF: u16 = 0xB5;
+pub const GUARD_REFLECT: u16 = 0xB6;
+
+// Knockdowns (missed techs), face up and face down
+pub const DOWN_BOUND_U: u16 = 0xB7;
+pub const DOWN_WAIT_U: u16 = 0xB8;
+pub const DOWN_BOUND_D: u16 = 0xBF;
+pub const DOWN_WAIT_D: u16 = 0xC0;
+
+// Techs
+pub const PASSIVE: u16 = 0xC7;
+pub const PASSIVE_STAND_F: u16 = 0xC8;
+pub const PASSIVE_STAND_B: u16 = 0xC9;
+pub const PASSIVE_WALL: u16 = 0xCA;
+pub const PASSIVE_WALL_JUMP: u16 = 0xCB;
+pub const PASSIVE_CEIL: u16 = 0xCC;
+
+// Ledge
+pub const CLIFF_CATCH: u16 = 0xFC;
+pub const CLIFF_WAIT: u16 = 0xFD;
+
+// Grabs and throws
+pub const CATCH: u16 = 0xD4;
+pub const CATCH_PULL: u16 = 0xD5;
+pub const CATCH_DASH: u16 = 0xD6;
+pub const CATCH_DASH_PULL: u16 = 0xD7;
+pub const CATCH_WAIT: u16 = 0xD8;
+pub const CATCH_ATTACK: u16 = 0xD9;
+pub const CATCH_CUT: u16 = 0xDA;
+pub const THROW_F: u16 = 0xDB;
+pub const THROW_B: u16 = 0xDC;
+pub const THROW_HI: u16 = 0xDD;
+pub const THROW_LW: u16 = 0xDE;
+
+// Held or thrown by an opponent
+pub const CAPTURE_PULLED_HI: u16 = 0xDF;
+pub const CAPTURE_FOOT: u16 = 0xE8;
+pub const THROWN_F: u16 = 0xEF;
+pub const THROWN_LW_WOMEN: u16 = 0xF3;
+
+// Caught by a command grab (Falcon's and Ganondorf's side-B, Kirby's and
+// Yoshi's neutral-B, Bowser's side-B and the like), in two blocks
+pub const COMMAND_GRABBED_START: u16 = 0x10A;
+pub const COMMAND_GRABBED_END: u16 = 0x130;
+pub const COMMAND_GRABBED_2_START: u16 = 0x147;
+pub const COMMAND_GRABBED_2_END: u16 = 0x152;
+
+// Rolls and dodges
+pub const ESCAPE_F: u16 = 0xE9;
+pub const ESCAPE_B: u16 = 0xEA;
+pub const ESCAPE: u16 = 0xEB;
+pub const ESCAPE_AIR: u16 = 0xEC;
+
+// Dropping through a platform
+pub const PASS: u16 = 0xF4;
+
+/// First character-specific action state (special moves and the like).
+pub const SPECIAL_START: u16 = 0x155;
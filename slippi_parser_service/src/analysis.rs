@@ -0,0 +1,1393 @@
+//! Move detection over replay frames: the per-player move counts and stats,
+//! and the game-wide events (stocks, first/last hit, move timeline) that the
+//! parser service and library users build on.
+
+use peppi::io::slippi::Version;
+use peppi::game::{Player, Team};
+use peppi::game::immutable::Game;
+use peppi::frame::immutable::Frame;
+use peppi::frame::transpose;
+use std::ops::Range;
+use std::collections::HashMap;
+use crate::{action_states, actions_per_minute, Combo, Death, FrameRange, IcsMode, MoveEvent, PlayerMoveData, StockEvent, OPENING_RESET_FRAMES};
+use crate::stages::{stage_bounds, StageBounds};
+
+// Every move counted in a game, techniques like wavedashes and dash dances
+// included, as {frame, port, move} events in frame then port order: what
+// --timeline prints, for Rust code using the crate directly. The whole game is
+// analyzed up front, then iterated, and Nana's moves are merged into Popo's.
+pub fn iter_moves(game: &Game, version: Version) -> impl Iterator<Item = MoveEvent> {
+    let (_, events) = extract_moves_from_frames(&game.frames, &game.start.players, version, game.start.stage, IcsMode::Merge, FrameRange::default(), OPENING_RESET_FRAMES);
+    events.timeline.into_iter()
+}
+
+// Melee's team colors
+pub fn team_color(team: &Team) -> String {
+    match team.color {
+        0 => "Red".to_string(),
+        1 => "Blue".to_string(),
+        2 => "Green".to_string(),
+        color => format!("Team {}", color),
+    }
+}
+
+// Map an external character ID (as stored in the game start block) to its display name
+pub fn character_name(character: u8) -> String {
+    let name = match character {
+        0 => "Captain Falcon",
+        1 => "Donkey Kong",
+        2 => "Fox",
+        3 => "Mr. Game & Watch",
+        4 => "Kirby",
+        5 => "Bowser",
+        6 => "Link",
+        7 => "Luigi",
+        8 => "Mario",
+        9 => "Marth",
+        10 => "Mewtwo",
+        11 => "Ness",
+        12 => "Peach",
+        13 => "Pikachu",
+        14 => "Ice Climbers",
+        15 => "Jigglypuff",
+        16 => "Samus",
+        17 => "Yoshi",
+        18 => "Zelda",
+        19 => "Sheik",
+        20 => "Falco",
+        21 => "Young Link",
+        22 => "Dr. Mario",
+        23 => "Roy",
+        24 => "Pichu",
+        25 => "Ganondorf",
+        26 => "Master Hand",
+        27 => "Wireframe (Male)",
+        28 => "Wireframe (Female)",
+        29 => "Giga Bowser",
+        30 => "Crazy Hand",
+        31 => "Sandbag",
+        32 => "Popo",
+        _ => return format!("Unknown({})", character),
+    };
+    
+    name.to_string()
+}
+
+// Extract moves from frame data
+pub fn extract_moves_from_frames(frames: &Frame, players: &[Player], version: Version, stage: u16, ics_mode: IcsMode, frame_range: FrameRange, combo_reset_frames: u32) -> (Vec<PlayerMoveData>, GameEvents) {
+    let mut player_moves: Vec<PlayerMoveData> = Vec::new();
+    
+    // Initialize move counters for each player
+    for player in players {
+        let netplay = player.netplay.as_ref();
+        player_moves.push(PlayerMoveData {
+            port: player.port.into(),
+            character: character_name(player.character),
+            display_name: netplay.map(|n| n.name.to_normalized()),
+            connect_code: netplay.map(|n| n.code.to_normalized()),
+            team: player.team.as_ref().map(team_color),
+            games_played: 1,
+            ..Default::default()
+        });
+    }
+    
+    // Iterate through all frames to extract moves
+    let window = frame_range.clamp(frames.len());
+    let mut events = analyze_frames(transpose_frames(frames, version, window.clone()), &mut player_moves, version, stage_bounds(stage), ics_mode, combo_reset_frames);
+    
+    // analyze_frames counts from the start of the window
+    for event in &mut events.stock_events {
+        event.frame += window.start as u32;
+    }
+    for event in &mut events.timeline {
+        event.frame += window.start as u32;
+    }
+    
+    (player_moves, events)
+}
+
+// Transpose each frame using the replay's own Slippi version, so version-gated
+// fields are decoded rather than dropped
+pub fn transpose_frames(frames: &Frame, version: Version, window: Range<usize>) -> impl Iterator<Item = transpose::Frame> + '_ {
+    window.map(move |frame_idx| frames.transpose_one(frame_idx, version))
+}
+
+// Game-wide results of analyze_frames, beside the per-player move data
+#[derive(Default)]
+pub struct GameEvents {
+    pub stock_events: Vec<StockEvent>,
+    // Ports that landed the first and last damaging hits, None if nobody was hit
+    pub first_hit_port: Option<u8>,
+    pub last_hit_port: Option<u8>,
+    // Every counted move as it starts, techniques included, in frame then port order
+    pub timeline: Vec<MoveEvent>,
+}
+
+// Run move detection over a sequence of transposed frames, returning the stocks lost and hits landed along the way
+pub fn analyze_frames(frames: impl Iterator<Item = transpose::Frame>, player_moves: &mut [PlayerMoveData], version: Version, stage: Option<StageBounds>, ics_mode: IcsMode, combo_reset_frames: u32) -> GameEvents {
+    // Last-seen action state per port, so a move is only counted on the frame it begins
+    let mut prev_states: HashMap<u8, u16> = HashMap::new();
+    let mut prev_percents: HashMap<u8, f32> = HashMap::new();
+    let mut frames_out_of_hitstun: HashMap<u8, u32> = HashMap::new();
+    let mut prev_inputs: HashMap<u8, ControllerInputs> = HashMap::new();
+    let mut ledgedashes: HashMap<u8, LedgedashPhase> = HashMap::new();
+    let mut ledge_stays: HashMap<u8, u32> = HashMap::new();
+    let mut wavedashes: HashMap<u8, WavedashTracker> = HashMap::new();
+    let mut movement: HashMap<u8, MovementTracker> = HashMap::new();
+    let mut shine_jumps: HashMap<u8, usize> = HashMap::new();
+    let mut dash_attacks: HashMap<u8, usize> = HashMap::new();
+    let mut platform_drops: HashMap<u8, usize> = HashMap::new();
+    let mut forms: HashMap<u8, u8> = HashMap::new();
+    let mut di: HashMap<u8, DiTracker> = HashMap::new();
+    let mut prev_stocks: HashMap<u8, u8> = HashMap::new();
+    let mut deaths = DeathTracker::default();
+    let mut events = GameEvents::default();
+    let mut combos: HashMap<u8, ComboTracker> = HashMap::new();
+    let mut edgeguards = EdgeguardTracker::default();
+    let mut attacks = AttackTracker::default();
+    
+    // Ice Climbers' follower (Nana) is tracked separately, then folded in at the end
+    let mut nana_moves: HashMap<u8, PlayerMoveData> = HashMap::new();
+    let mut nana_prev_states: HashMap<u8, u16> = HashMap::new();
+    
+    // Nana's moves are either Popo's own or split out under a suffix
+    let nana_suffix = match ics_mode {
+        IcsMode::Merge => "",
+        IcsMode::Split => "_nana",
+    };
+    
+    for (frame_idx, frame) in frames.enumerate() {
+        for port_data in &frame.ports {
+            // Frames only list occupied ports, so match on the port itself rather than its position
+            let port: u8 = port_data.port.into();
+            if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == port) {
+                let prev_state = prev_states.insert(player_data.port, port_data.leader.pre.state);
+                let prev_input = prev_inputs.insert(player_data.port, controller_inputs(&port_data.leader.pre));
+                
+                // Analyze pre-frame data for inputs and action states
+                analyze_frame_for_moves(&port_data.leader, prev_state, prev_input, player_data, frame_idx, version);
+                
+                let phase = ledgedashes.entry(player_data.port).or_default();
+                *phase = track_ledgedash(*phase, &port_data.leader, player_data);
+                track_ledge(&mut ledge_stays, &port_data.leader, player_data);
+                track_wavedash(wavedashes.entry(player_data.port).or_default(), &port_data.leader, prev_state, player_data, version);
+                analyze_movement(movement.entry(player_data.port).or_default(), &port_data.leader, prev_state, frame_idx, player_data);
+                track_shine_cancels(&mut shine_jumps, &port_data.leader, prev_state, frame_idx, player_data);
+                if track_dacus(&mut dash_attacks, &port_data.leader, prev_state, frame_idx, player_data) {
+                    // The DACUS keeps the frame of the dash attack it started as,
+                    // which is relabelled in the timeline rather than listed twice
+                    player_data.started_moves.pop();
+                    if let Some(event) = events.timeline.iter_mut().rev().find(|event| event.port == player_data.port && event.move_name == "dash_attack") {
+                        event.move_name = "dacus".to_string();
+                    }
+                }
+                if let Some(stage) = &stage {
+                    track_platforms(&mut platform_drops, &port_data.leader, prev_state, frame_idx, stage, player_data);
+                }
+                track_di(di.entry(player_data.port).or_default(), &port_data.leader, player_data);
+                track_transformations(&mut forms, &port_data.leader, player_data);
+                
+                // Only Ice Climbers have a follower
+                if let Some(follower) = &port_data.follower {
+                    let nana = nana_moves.entry(player_data.port).or_insert_with(|| PlayerMoveData {
+                        port: player_data.port,
+                        character: player_data.character.clone(),
+                        ..Default::default()
+                    });
+                    let prev_state = nana_prev_states.insert(player_data.port, follower.pre.state);
+                    analyze_frame_for_moves(follower, prev_state, None, nana, frame_idx, version);
+                }
+            }
+        }
+        
+        // Everything counted this frame, techniques included, goes on the timeline
+        for player_data in player_moves.iter_mut() {
+            for move_name in player_data.started_moves.drain(..) {
+                events.timeline.push(MoveEvent { frame: frame_idx as u32, port: player_data.port, move_name });
+            }
+            if let Some(nana) = nana_moves.get_mut(&player_data.port) {
+                for move_name in nana.started_moves.drain(..) {
+                    events.timeline.push(MoveEvent { frame: frame_idx as u32, port: player_data.port, move_name: format!("{}{}", move_name, nana_suffix) });
+                }
+            }
+        }
+        
+        if let Some(attacker) = track_damage(&frame, &mut prev_percents, &mut frames_out_of_hitstun, player_moves) {
+            events.first_hit_port.get_or_insert(attacker);
+            events.last_hit_port = Some(attacker);
+        }
+        track_deaths(&frame, frame_idx as u32, &mut deaths, player_moves);
+        track_stocks(&frame, frame_idx, &mut prev_stocks, &mut events.stock_events);
+        detect_combos(&frame, frame_idx, &mut combos, combo_reset_frames, player_moves);
+        track_attack_outcomes(&frame, &mut attacks, player_moves);
+        if let Some(stage) = &stage {
+            detect_edgeguards(&frame, stage, &mut edgeguards, player_moves);
+        }
+    }
+    
+    // Attacks still going when the frames run out
+    for (attacker, attack) in attacks.active {
+        finish_attack(attacker, attack, player_moves);
+    }
+    
+    // Combos still going when the frames run out
+    for tracker in combos.into_values() {
+        if let Some((attacker, combo)) = tracker.active {
+            finish_combo(attacker, combo, player_moves);
+        }
+    }
+    
+    // Nana's inputs mirror Popo's, so only her moves are kept, not her APM
+    for (port, nana) in nana_moves {
+        if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == port) {
+            for (move_name, count) in nana.moves {
+                *player_data.moves.entry(format!("{}{}", move_name, nana_suffix)).or_insert(0) += count;
+            }
+            for (move_name, count) in nana.last_stock_moves {
+                *player_data.last_stock_moves.entry(format!("{}{}", move_name, nana_suffix)).or_insert(0) += count;
+            }
+        }
+    }
+    
+    for player_data in player_moves.iter_mut() {
+        // prev_stocks is left holding each player's stocks on the last frame
+        let survived = prev_stocks.get(&player_data.port).is_some_and(|&stocks| stocks > 0);
+        player_data.stocks_used = player_data.deaths.len() as u32 + u32::from(survived);
+        player_data.apm = actions_per_minute(player_data.actions, player_data.frames_played);
+        player_data.move_percentages = player_data.compute_move_percentages();
+        player_data.moves_per_minute = player_data.compute_moves_per_minute();
+        player_data.l_cancel_success_rate = player_data.compute_l_cancel_success_rate();
+        player_data.grab_success_rate = player_data.compute_grab_success_rate();
+        player_data.actionable_ratio = player_data.compute_actionable_ratio();
+        player_data.average_galint = player_data.compute_average_galint();
+        player_data.average_ledge_time = player_data.compute_average_ledge_time();
+        player_data.average_death_percent = player_data.compute_average_death_percent(false);
+        player_data.compute_wavedash_averages();
+    }
+    
+    // Popo's and Nana's moves on the same frame stay in the order they were seen
+    events.timeline.sort_by_key(|event| (event.frame, event.port));
+    events
+}
+
+// Count a move the player started, and in last_stock_moves too when they were
+// on their last stock. Every count in `moves` goes through here, so the rest of
+// the game is always `moves` minus `last_stock_moves`. Nana's frames carry
+// Popo's stock count, which the two share. The move is also queued for the
+// timeline, which analyze_frames fills in at the end of each frame.
+fn count_move(player_data: &mut PlayerMoveData, move_name: &str, stocks: u8) {
+    *player_data.moves.entry(move_name.to_string()).or_insert(0) += 1;
+    player_data.started_moves.push(move_name.to_string());
+    if stocks == 1 {
+        *player_data.last_stock_moves.entry(move_name.to_string()).or_insert(0) += 1;
+    }
+}
+
+// Take back a count made by count_move, dropping the move once none are left
+fn uncount_move(counts: &mut HashMap<String, u32>, move_name: &str) {
+    if let Some(count) = counts.get_mut(move_name) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(move_name);
+        }
+    }
+}
+
+// Per-victim state for detect_combos: the combo being taken, if any
+#[derive(Default)]
+struct ComboTracker {
+    prev_percent: Option<f32>,
+    prev_stocks: Option<u8>,
+    frames_out_of_hitstun: u32,
+    active: Option<(u8, Combo)>,
+}
+
+// Group hits by the same attacker on a victim into combos. A combo ends when
+// the victim has been out of hitstun for `reset_frames`, when someone else
+// hits them, or when they lose a stock (a kill). Finished combos of two or
+// more hits are added to the attacker's player data; those ended by the
+// victim getting back to neutral also count as dropped and escaped.
+fn detect_combos(frame: &transpose::Frame, frame_idx: usize, trackers: &mut HashMap<u8, ComboTracker>, reset_frames: u32, player_moves: &mut [PlayerMoveData]) {
+    for port_data in &frame.ports {
+        let victim: u8 = port_data.port.into();
+        let post = &port_data.leader.post;
+        let tracker = trackers.entry(victim).or_default();
+        let prev_percent = tracker.prev_percent.replace(post.percent);
+        let prev_stocks = tracker.prev_stocks.replace(post.stocks);
+        
+        if prev_stocks.is_some_and(|stocks| post.stocks < stocks) {
+            if let Some((attacker, mut combo)) = tracker.active.take() {
+                combo.killed = true;
+                finish_combo(attacker, combo, player_moves);
+            }
+        }
+        
+        let damage = prev_percent.map_or(0.0, |prev| post.percent - prev);
+        let attacker = post.last_hit_by;
+        if damage > 0.0 && attacker != victim {
+            match &mut tracker.active {
+                Some((combo_attacker, combo)) if *combo_attacker == attacker => {
+                    combo.hits += 1;
+                    combo.damage += damage;
+                }
+                _ => {
+                    if let Some((prev_attacker, combo)) = tracker.active.take() {
+                        finish_combo(prev_attacker, combo, player_moves);
+                    }
+                    
+                    // The attacker's current state is the move that opened the combo
+                    let attacker_state = frame.ports.iter().find(|p| u8::from(p.port) == attacker).map(|p| p.leader.pre.state);
+                    let character = player_moves.iter().find(|p| p.port == attacker).map_or("", |p| p.character.as_str());
+                    let starting_move = attacker_state
+                        .and_then(|state| identify_move_from_action_state(state, character))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    
+                    tracker.active = Some((attacker, Combo {
+                        victim,
+                        start_frame: frame_idx as u32,
+                        starting_move,
+                        hits: 1,
+                        damage,
+                        killed: false,
+                    }));
+                }
+            }
+        }
+        
+        tracker.frames_out_of_hitstun = if is_in_hitstun(post.state) { 0 } else { tracker.frames_out_of_hitstun + 1 };
+        if tracker.frames_out_of_hitstun >= reset_frames {
+            if let Some((attacker, combo)) = tracker.active.take() {
+                if combo.hits >= 2 {
+                    count_combo_escape(attacker, victim, player_moves);
+                }
+                finish_combo(attacker, combo, player_moves);
+            }
+        }
+    }
+}
+
+// Single hits are openings, not combos
+fn finish_combo(attacker: u8, combo: Combo, player_moves: &mut [PlayerMoveData]) {
+    if combo.hits < 2 {
+        return;
+    }
+    if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == attacker) {
+        player_data.combos.push(combo);
+    }
+}
+
+fn count_combo_escape(attacker: u8, victim: u8, player_moves: &mut [PlayerMoveData]) {
+    for player_data in player_moves.iter_mut() {
+        if player_data.port == attacker {
+            player_data.combos_dropped += 1;
+        } else if player_data.port == victim {
+            player_data.combos_escaped += 1;
+        }
+    }
+}
+
+// An attack in progress: its action state, frames spent in it so far, and
+// whether it has connected yet
+struct ActiveAttack {
+    state: u16,
+    move_name: String,
+    frames: usize,
+    hit: bool,
+}
+
+#[derive(Default)]
+struct AttackTracker {
+    prev_states: HashMap<u8, u16>,
+    prev_percents: HashMap<u8, f32>,
+    prev_hitstun: HashMap<u8, bool>,
+    active: HashMap<u8, ActiveAttack>,
+}
+
+// Sort each attack into a hit or a whiff. An attack lasts as long as the
+// attacker stays in its action state; it hits if, meanwhile, an opponent last
+// hit by the attacker takes damage or enters hitstun. Shielded attacks whiff.
+fn track_attack_outcomes(frame: &transpose::Frame, tracker: &mut AttackTracker, player_moves: &mut [PlayerMoveData]) {
+    // Who made contact this frame
+    let mut contact: Vec<u8> = Vec::new();
+    for port_data in &frame.ports {
+        let victim: u8 = port_data.port.into();
+        let post = &port_data.leader.post;
+        let took_damage = tracker.prev_percents.insert(victim, post.percent).is_some_and(|prev| post.percent > prev);
+        let in_hitstun = is_in_hitstun(post.state);
+        let was_in_hitstun = tracker.prev_hitstun.insert(victim, in_hitstun);
+        let entered_hitstun = in_hitstun && was_in_hitstun == Some(false);
+        if (took_damage || entered_hitstun) && post.last_hit_by != victim {
+            contact.push(post.last_hit_by);
+        }
+    }
+    
+    for port_data in &frame.ports {
+        let attacker: u8 = port_data.port.into();
+        let state = port_data.leader.pre.state;
+        let prev_state = tracker.prev_states.insert(attacker, state);
+        
+        if prev_state != Some(state) {
+            if let Some(attack) = tracker.active.remove(&attacker) {
+                // A dash attack cancelled into an up-smash (DACUS) never came
+                // out, so it is neither a hit nor a whiff
+                let dacus = attack.state == action_states::ATTACK_DASH && state == action_states::ATTACK_HI4 && attack.frames <= DACUS_WINDOW && !attack.hit;
+                if !dacus {
+                    finish_attack(attacker, attack, player_moves);
+                }
+            }
+            let character = player_moves.iter().find(|p| p.port == attacker).map_or("", |p| p.character.as_str());
+            let move_name = identify_move_from_action_state(state, character).filter(|name| !matches!(name.as_str(), "jump" | "double_jump" | "grab"));
+            if let Some(move_name) = move_name {
+                tracker.active.insert(attacker, ActiveAttack { state, move_name, frames: 0, hit: false });
+            }
+        }
+        
+        if let Some(attack) = tracker.active.get_mut(&attacker) {
+            attack.frames += 1;
+            if attack.state == state && contact.contains(&attacker) {
+                attack.hit = true;
+            }
+        }
+    }
+}
+
+fn finish_attack(attacker: u8, attack: ActiveAttack, player_moves: &mut [PlayerMoveData]) {
+    if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == attacker) {
+        let outcome = player_data.move_outcomes.entry(attack.move_name).or_default();
+        if attack.hit {
+            outcome.hits += 1;
+        } else {
+            outcome.whiffs += 1;
+        }
+    }
+}
+
+// Frames after an offstage player gets back during which losing a stock still
+// counts as a successful edgeguard (e.g. a spike that kills after they land)
+pub const EDGEGUARD_KILL_WINDOW: u32 = 60;
+
+// An offstage player, and the opponents who have gone after them
+#[derive(Default)]
+struct EdgeguardSituation {
+    attackers: Vec<u8>,
+    frames_since_recovered: Option<u32>,
+}
+
+#[derive(Default)]
+struct EdgeguardTracker {
+    prev_states: HashMap<u8, u16>,
+    prev_stocks: HashMap<u8, u8>,
+    situations: HashMap<u8, EdgeguardSituation>,
+}
+
+// Count an edgeguard attempt the first time each opponent attacks or grabs the
+// ledge while a player is offstage (past the ledge and airborne), and a success
+// for each of them if that player then loses a stock before getting back, or
+// within EDGEGUARD_KILL_WINDOW frames of it. Teammates don't edgeguard each other.
+fn detect_edgeguards(frame: &transpose::Frame, stage: &StageBounds, tracker: &mut EdgeguardTracker, player_moves: &mut [PlayerMoveData]) {
+    let mut going_after: Vec<u8> = Vec::new();
+    for port_data in &frame.ports {
+        let port: u8 = port_data.port.into();
+        let state = port_data.leader.pre.state;
+        if tracker.prev_states.insert(port, state) == Some(state) {
+            continue;
+        }
+        let character = player_moves.iter().find(|p| p.port == port).map_or("", |p| p.character.as_str());
+        let attacked = identify_move_from_action_state(state, character).is_some_and(|name| name != "jump" && name != "double_jump");
+        if attacked || state == action_states::CLIFF_CATCH {
+            going_after.push(port);
+        }
+    }
+    
+    for port_data in &frame.ports {
+        let victim: u8 = port_data.port.into();
+        let post = &port_data.leader.post;
+        let lost_stock = tracker.prev_stocks.insert(victim, post.stocks).is_some_and(|prev| post.stocks < prev);
+        if lost_stock {
+            if let Some(situation) = tracker.situations.remove(&victim) {
+                for attacker in situation.attackers {
+                    if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == attacker) {
+                        player_data.edgeguards.successes += 1;
+                    }
+                }
+            }
+            continue;
+        }
+        
+        // Hanging on the ledge is past it too, but that's a recovery that made it
+        let on_ledge = matches!(post.state, action_states::CLIFF_CATCH | action_states::CLIFF_WAIT);
+        let airborne = post.airborne.is_none_or(|airborne| airborne != 0);
+        if stage.is_offstage(post.position.x) && airborne && !on_ledge {
+            let victim_team = player_moves.iter().find(|p| p.port == victim).and_then(|p| p.team.clone());
+            let situation = tracker.situations.entry(victim).or_default();
+            situation.frames_since_recovered = None;
+            for &attacker in &going_after {
+                if attacker == victim || situation.attackers.contains(&attacker) {
+                    continue;
+                }
+                let Some(player_data) = player_moves.iter_mut().find(|p| p.port == attacker) else { continue };
+                if victim_team.is_some() && player_data.team == victim_team {
+                    continue;
+                }
+                player_data.edgeguards.attempts += 1;
+                situation.attackers.push(attacker);
+            }
+        } else if let Some(situation) = tracker.situations.get_mut(&victim) {
+            let frames = situation.frames_since_recovered.get_or_insert(0);
+            *frames += 1;
+            if *frames > EDGEGUARD_KILL_WINDOW {
+                tracker.situations.remove(&victim);
+            }
+        }
+    }
+}
+
+// Record a stock event whenever a port's stock count drops. Counts start from
+// the first frame seen rather than the starting stocks in the game start block,
+// so a --start-frame window doesn't report stocks lost before it as one drop.
+fn track_stocks(frame: &transpose::Frame, frame_idx: usize, prev_stocks: &mut HashMap<u8, u8>, stock_events: &mut Vec<StockEvent>) {
+    for port_data in &frame.ports {
+        let port: u8 = port_data.port.into();
+        let stocks = port_data.leader.post.stocks;
+        if let Some(prev) = prev_stocks.insert(port, stocks) {
+            if stocks < prev {
+                stock_events.push(StockEvent { frame: frame_idx as u32, port, remaining_stocks: stocks });
+            }
+        }
+    }
+}
+
+// Stick angles (degrees below horizontal) up to this give the longest wavedash
+const PERFECT_WAVEDASH_MAX_ANGLE: f32 = 20.0;
+
+#[derive(Clone, Copy)]
+struct Airdodge {
+    start_x: f32,
+    angle: f32,
+    out_of_shield: bool,
+}
+
+// Per-player wavedash state: the jump it came from, the airdodge, and the
+// airdodge being measured while the player slides through landing lag
+#[derive(Default)]
+struct WavedashTracker {
+    jumped_from_shield: bool,
+    airdodge: Option<Airdodge>,
+    sliding: Option<Airdodge>,
+}
+
+// Measure each wavedash's airdodge angle and slide distance, and classify it
+// as out of shield, perfect (near-horizontal) or shallow (steeper, shorter)
+fn track_wavedash(tracker: &mut WavedashTracker, leader: &transpose::Data, prev_state: Option<u16>, player_data: &mut PlayerMoveData, version: Version) {
+    let state = leader.pre.state;
+    let entered = prev_state != Some(state);
+    
+    // The slide ends with landing lag
+    if state != action_states::LANDING_FALL_SPECIAL {
+        if let Some(airdodge) = tracker.sliding.take() {
+            let classification = if airdodge.out_of_shield {
+                "wavedash_oos"
+            } else if airdodge.angle <= PERFECT_WAVEDASH_MAX_ANGLE {
+                "perfect_wavedash"
+            } else {
+                "shallow_wavedash"
+            };
+            count_move(player_data, classification, leader.post.stocks);
+            player_data.total_wavedash_angle += airdodge.angle;
+            player_data.total_wavedash_distance += (leader.post.position.x - airdodge.start_x).abs();
+            player_data.wavedash_samples += 1;
+        }
+    }
+    
+    if !entered {
+        return;
+    }
+    
+    match state {
+        action_states::KNEE_BEND => {
+            tracker.jumped_from_shield = prev_state.is_some_and(|prev| (action_states::GUARD_ON..=action_states::GUARD_REFLECT).contains(&prev));
+        }
+        action_states::ESCAPE_AIR => {
+            let stick = leader.pre.joystick;
+            tracker.airdodge = Some(Airdodge {
+                start_x: leader.post.position.x,
+                angle: (-stick.y).atan2(stick.x.abs()).to_degrees().max(0.0),
+                out_of_shield: tracker.jumped_from_shield,
+            });
+        }
+        action_states::LANDING_FALL_SPECIAL => {
+            // Same grounded check as the wavedash count
+            let grounded = if version.gte(2, 0) { leader.post.airborne == Some(0) } else { true };
+            if grounded {
+                tracker.sliding = tracker.airdodge.take();
+            }
+        }
+        _ => {}
+    }
+}
+
+// Frames allowed between two dashes in opposite directions for them to count as a dash-dance
+const DASH_DANCE_WINDOW: usize = 20;
+
+// Per-player movement state: the last dash (frame and direction, 1 for right
+// and -1 for left), whether the current turnaround came out of a dash, the
+// facing on the previous frame, the facing a jumpsquat was entered from a dash
+// with, and whether the current jump turned the character around mid-dash
+#[derive(Default)]
+struct MovementTracker {
+    last_dash: Option<(usize, f32)>,
+    turned_from_dash: bool,
+    facing: f32,
+    dash_jump_facing: Option<f32>,
+    turnaround_jump: bool,
+}
+
+// Count dash-dances (a dash reversed into the other direction within
+// DASH_DANCE_WINDOW frames, once per reversal), pivots (dash -> turn ->
+// standing), RARs (dash -> jump that turns the character around -> back-air
+// before doing anything else) and turnaround-Bs (a special that flips the
+// character's facing on the frame it starts). Breaking into a run ends the
+// dash-dance.
+fn analyze_movement(tracker: &mut MovementTracker, leader: &transpose::Data, prev_state: Option<u16>, frame_idx: usize, player_data: &mut PlayerMoveData) {
+    use action_states::*;
+    
+    let state = leader.pre.state;
+    let facing = leader.post.direction;
+    let prev_facing = std::mem::replace(&mut tracker.facing, facing);
+    let turned_around = prev_facing != 0.0 && facing == -prev_facing;
+    
+    // Dashing back during a dash restarts the dash state, so reversals are
+    // spotted by direction rather than by entering the state
+    if state == DASH {
+        // The stick says which way the dash goes; fall back to facing if it's near neutral
+        let stick_x = leader.pre.joystick.x;
+        let direction = if stick_x.abs() > 0.1 { stick_x.signum() } else { leader.post.direction.signum() };
+        let reversed = tracker.last_dash.is_some_and(|(_, dash_direction)| dash_direction != direction);
+        if reversed || prev_state != Some(DASH) {
+            if reversed && tracker.last_dash.is_some_and(|(dash_frame, _)| frame_idx - dash_frame <= DASH_DANCE_WINDOW) {
+                count_move(player_data, "dash_dance", leader.post.stocks);
+            }
+            tracker.last_dash = Some((frame_idx, direction));
+        }
+    }
+    if prev_state == Some(state) {
+        return;
+    }
+    
+    match state {
+        RUN => tracker.last_dash = None,
+        WAIT if tracker.turned_from_dash && prev_state == Some(TURN) => {
+            count_move(player_data, "pivot", leader.post.stocks);
+        }
+        KNEE_BEND if matches!(prev_state, Some(DASH | RUN)) => tracker.dash_jump_facing = Some(prev_facing),
+        // Holding back during jumpsquat turns the character around while keeping the dash's momentum
+        JUMP_F | JUMP_B => {
+            tracker.turnaround_jump = tracker.dash_jump_facing.is_some_and(|dash_facing| dash_facing != 0.0 && facing == -dash_facing);
+        }
+        ATTACK_AIR_B if tracker.turnaround_jump => {
+            count_move(player_data, "rar", leader.post.stocks);
+        }
+        _ if state >= SPECIAL_START && turned_around => {
+            // Only the first state of a special, not a later part of the same move
+            let character = player_data.character.as_str();
+            let special = identify_move_from_action_state(state, character);
+            if special.is_some() && special != prev_state.and_then(|prev| identify_move_from_action_state(prev, character)) {
+                count_move(player_data, "turnaround_b", leader.post.stocks);
+            }
+        }
+        _ => {}
+    }
+    tracker.turned_from_dash = state == TURN && prev_state == Some(DASH);
+    if state != KNEE_BEND {
+        tracker.dash_jump_facing = None;
+    }
+    if !matches!(state, JUMP_F | JUMP_B | FALL) {
+        tracker.turnaround_jump = false;
+    }
+}
+
+// Frames allowed from jump-cancelling a shine to the next shine or grab
+const SHINE_CANCEL_WINDOW: usize = 8;
+
+// Count spacies' multishines (shine -> jump-cancel -> shine) and shine-grabs
+// (shine -> jump-cancel -> grab out of jumpsquat). `shine_jumps` holds, per
+// port, the frame a shine was last jump-cancelled on, until the follow-up
+// comes or the window passes.
+fn track_shine_cancels(shine_jumps: &mut HashMap<u8, usize>, leader: &transpose::Data, prev_state: Option<u16>, frame_idx: usize, player_data: &mut PlayerMoveData) {
+    use action_states::*;
+    
+    let state = leader.pre.state;
+    if prev_state == Some(state) {
+        return;
+    }
+    let character = player_data.character.as_str();
+    let is_shine = |state: u16| identify_move_from_action_state(state, character).as_deref() == Some("shine");
+    
+    if state == KNEE_BEND && prev_state.is_some_and(is_shine) {
+        shine_jumps.insert(player_data.port, frame_idx);
+        return;
+    }
+    let Some(jumped_at) = shine_jumps.get(&player_data.port).copied() else {
+        return;
+    };
+    if frame_idx - jumped_at > SHINE_CANCEL_WINDOW {
+        shine_jumps.remove(&player_data.port);
+        return;
+    }
+    
+    let follow_up = if is_shine(state) && !prev_state.is_some_and(is_shine) {
+        "multishine"
+    } else if state == CATCH {
+        "shine_grab"
+    } else {
+        return;
+    };
+    count_move(player_data, follow_up, leader.post.stocks);
+    shine_jumps.remove(&player_data.port);
+}
+
+// Frames into a dash attack it can still be cancelled into an up-smash
+const DACUS_WINDOW: usize = 8;
+
+// Count dash attacks cancelled into an up-smash early enough to be a DACUS as
+// "dacus" rather than "dash_attack"; the up-smash is still counted as usmash.
+// `dash_attacks` holds, per port, the frame its current dash attack began.
+// Returns whether a dash attack was recounted as a DACUS this frame.
+fn track_dacus(dash_attacks: &mut HashMap<u8, usize>, leader: &transpose::Data, prev_state: Option<u16>, frame_idx: usize, player_data: &mut PlayerMoveData) -> bool {
+    use action_states::*;
+    
+    let state = leader.pre.state;
+    if prev_state == Some(state) {
+        return false;
+    }
+    if state == ATTACK_DASH {
+        dash_attacks.insert(player_data.port, frame_idx);
+        return false;
+    }
+    let Some(started_at) = dash_attacks.remove(&player_data.port) else {
+        return false;
+    };
+    if state != ATTACK_HI4 || prev_state != Some(ATTACK_DASH) || frame_idx - started_at > DACUS_WINDOW {
+        return false;
+    }
+    
+    // The dash attack began on the same stock, a few frames earlier
+    uncount_move(&mut player_data.moves, "dash_attack");
+    if leader.post.stocks == 1 {
+        uncount_move(&mut player_data.last_stock_moves, "dash_attack");
+    }
+    count_move(player_data, "dacus", leader.post.stocks);
+    true
+}
+
+// The in-game (internal) character IDs Zelda and Sheik switch between when
+// transforming, which post-frame data reports frame by frame
+pub fn form_name(internal_character: u8) -> Option<&'static str> {
+    match internal_character {
+        7 => Some("Sheik"),
+        19 => Some("Zelda"),
+        _ => None,
+    }
+}
+
+// Count transformations: the in-game character switching between Zelda and
+// Sheik. `forms` holds each port's character on its last frame.
+fn track_transformations(forms: &mut HashMap<u8, u8>, leader: &transpose::Data, player_data: &mut PlayerMoveData) {
+    let character = leader.post.character;
+    if form_name(character).is_none() {
+        return;
+    }
+    if forms.insert(player_data.port, character).is_some_and(|prev| prev != character) {
+        player_data.transformations += 1;
+    }
+}
+
+// Frames allowed from dropping through a platform to an aerial
+const PLATFORM_DROP_AERIAL_WINDOW: usize = 12;
+
+// Count drops through a platform (including shield drops), aerials started
+// within PLATFORM_DROP_AERIAL_WINDOW frames of one, and wavelands (airdodges
+// landing on a platform), using the stage's platform positions.
+// `platform_drops` holds, per port, the frame of the last drop until an aerial
+// uses it or the window passes.
+fn track_platforms(platform_drops: &mut HashMap<u8, usize>, leader: &transpose::Data, prev_state: Option<u16>, frame_idx: usize, stage: &StageBounds, player_data: &mut PlayerMoveData) {
+    use action_states::*;
+    
+    let state = leader.pre.state;
+    if prev_state == Some(state) {
+        return;
+    }
+    let position = leader.post.position;
+    let on_platform = stage.platform_at(position.x, position.y).is_some();
+    
+    let counted = match state {
+        PASS if on_platform => {
+            platform_drops.insert(player_data.port, frame_idx);
+            "platform_drop"
+        }
+        ATTACK_AIR_N..=ATTACK_AIR_LW => match platform_drops.remove(&player_data.port) {
+            Some(dropped_at) if frame_idx - dropped_at <= PLATFORM_DROP_AERIAL_WINDOW => "platform_drop_aerial",
+            _ => return,
+        },
+        LANDING_FALL_SPECIAL if prev_state == Some(ESCAPE_AIR) && on_platform => "platform_waveland",
+        _ => return,
+    };
+    count_move(player_data, counted, leader.post.stocks);
+}
+
+// Frames allowed between letting go of the ledge and landing the airdodge
+const LEDGEDASH_WINDOW: u32 = 40;
+
+// Progress through a ledgedash: ledge -> drop/jump -> airdodge -> landing,
+// then measuring how long ledge intangibility outlasts the landing lag
+#[derive(Clone, Copy, Default)]
+enum LedgedashPhase {
+    #[default]
+    Idle,
+    OnLedge,
+    OffLedge { frames: u32, airdodged: bool },
+    Landed { lag_frames: i32, intangible_frames: i32, vulnerable: bool },
+}
+
+// Count ledge grabs and time each stay on the ledge, keyed by port in
+// `ledge_stays` while it lasts. Leaving the ledge in hitstun means the player
+// was hit off it; those stays are counted as knockoffs and left out of the
+// average.
+fn track_ledge(ledge_stays: &mut HashMap<u8, u32>, leader: &transpose::Data, player_data: &mut PlayerMoveData) {
+    let state = leader.pre.state;
+    if state == action_states::CLIFF_CATCH || state == action_states::CLIFF_WAIT {
+        let frames = ledge_stays.entry(player_data.port).or_insert_with(|| {
+            player_data.ledge_grabs += 1;
+            0
+        });
+        *frames += 1;
+        return;
+    }
+    
+    if let Some(frames) = ledge_stays.remove(&player_data.port) {
+        if is_in_hitstun(state) {
+            player_data.ledge_knockoffs += 1;
+        } else {
+            player_data.total_ledge_frames += frames;
+            player_data.ledge_releases += 1;
+        }
+    }
+}
+
+// Advance a player's ledgedash tracking by one frame. A landing counts as a
+// "ledgedash" move; GALINT (intangible frames left once actionable) needs the
+// hurtbox state recorded from Slippi 2.0 onwards.
+fn track_ledgedash(phase: LedgedashPhase, leader: &transpose::Data, player_data: &mut PlayerMoveData) -> LedgedashPhase {
+    let state = leader.pre.state;
+    if state == action_states::CLIFF_CATCH || state == action_states::CLIFF_WAIT {
+        return LedgedashPhase::OnLedge;
+    }
+    
+    match phase {
+        LedgedashPhase::Idle => LedgedashPhase::Idle,
+        LedgedashPhase::OnLedge => track_ledgedash(LedgedashPhase::OffLedge { frames: 0, airdodged: false }, leader, player_data),
+        LedgedashPhase::OffLedge { frames, airdodged } => {
+            let airdodged = airdodged || state == action_states::ESCAPE_AIR;
+            if airdodged && state == action_states::LANDING_FALL_SPECIAL && leader.post.airborne != Some(1) {
+                count_move(player_data, "ledgedash", leader.post.stocks);
+                if leader.post.hurtbox_state.is_none() {
+                    return LedgedashPhase::Idle;
+                }
+                return track_ledgedash(LedgedashPhase::Landed { lag_frames: 0, intangible_frames: 0, vulnerable: false }, leader, player_data);
+            }
+            if frames >= LEDGEDASH_WINDOW {
+                return LedgedashPhase::Idle;
+            }
+            LedgedashPhase::OffLedge { frames: frames + 1, airdodged }
+        }
+        LedgedashPhase::Landed { mut lag_frames, mut intangible_frames, mut vulnerable } => {
+            let in_lag = state == action_states::LANDING_FALL_SPECIAL;
+            if in_lag {
+                lag_frames += 1;
+            }
+            vulnerable = vulnerable || leader.post.hurtbox_state == Some(0);
+            if !vulnerable {
+                intangible_frames += 1;
+            }
+            
+            if in_lag || !vulnerable {
+                return LedgedashPhase::Landed { lag_frames, intangible_frames, vulnerable };
+            }
+            player_data.total_galint += intangible_frames - lag_frames;
+            player_data.galint_samples += 1;
+            LedgedashPhase::Idle
+        }
+    }
+}
+
+// Controller state reduced to what matters for counting actions
+#[derive(Clone, Copy, Default, PartialEq)]
+struct ControllerInputs {
+    buttons: u16,
+    joystick: u8,
+    cstick: u8,
+}
+
+fn controller_inputs(pre: &transpose::Pre) -> ControllerInputs {
+    ControllerInputs {
+        buttons: pre.buttons_physical,
+        joystick: stick_region(pre.joystick),
+        cstick: stick_region(pre.cstick),
+    }
+}
+
+// Stick coordinates closer to the center than this read as neutral
+const STICK_DEADZONE: f32 = 0.2875;
+
+// Stick position as 0 (deadzone) or one of 8 directions, numbered clockwise from up
+fn stick_region(stick: transpose::Position) -> u8 {
+    let x = if stick.x >= STICK_DEADZONE { 1 } else if stick.x <= -STICK_DEADZONE { -1 } else { 0 };
+    let y = if stick.y >= STICK_DEADZONE { 1 } else if stick.y <= -STICK_DEADZONE { -1 } else { 0 };
+    match (x, y) {
+        (0, 1) => 1,
+        (1, 1) => 2,
+        (1, 0) => 3,
+        (1, -1) => 4,
+        (0, -1) => 5,
+        (-1, -1) => 6,
+        (-1, 0) => 7,
+        (-1, 1) => 8,
+        _ => 0,
+    }
+}
+
+// Melee rotates knockback by up to 18 degrees, in proportion to how far the
+// stick is held perpendicular to it
+const MAX_DI_DEGREES: f32 = 18.0;
+
+#[derive(Default)]
+struct DiTracker {
+    // Hit and still in hitlag, so DI is yet to be applied
+    awaiting_di: bool,
+    prev_stick_region: u8,
+}
+
+// DI is the stick position on the first frame after hitlag, when knockback is
+// applied; each move of the stick into a new region during hitlag is an SDI
+// input. Needs the hitlag counter (Slippi 3.8+).
+fn track_di(tracker: &mut DiTracker, leader: &transpose::Data, player_data: &mut PlayerMoveData) {
+    let stick = leader.pre.joystick;
+    let region = stick_region(stick);
+    let prev_region = std::mem::replace(&mut tracker.prev_stick_region, region);
+    let post = &leader.post;
+    if !is_in_hitstun(post.state) {
+        tracker.awaiting_di = false;
+        return;
+    }
+    
+    if post.hitlag.is_some_and(|frames| frames > 0.0) {
+        tracker.awaiting_di = true;
+        if region != 0 && region != prev_region {
+            player_data.sdi_inputs += 1;
+        }
+        return;
+    }
+    
+    if std::mem::take(&mut tracker.awaiting_di) {
+        let di = post.velocities.and_then(|v| classify_di(v.knockback_x, v.knockback_y, stick));
+        if let Some(di) = di {
+            *player_data.di.entry(di.to_string()).or_insert(0) += 1;
+        }
+    }
+}
+
+// How a stick position DIs a launch: "survival" rotates an upward launch
+// towards the 45 degree diagonal, "in" rotates it towards vertical and "out"
+// towards horizontal. Sticks (nearly) parallel to the knockback don't change
+// it. None when there is no knockback.
+pub fn classify_di(knockback_x: f32, knockback_y: f32, stick: transpose::Position) -> Option<&'static str> {
+    let speed = knockback_x.hypot(knockback_y);
+    if speed == 0.0 {
+        return None;
+    }
+    let (x, y) = (knockback_x / speed, knockback_y / speed);
+    
+    // Positive rotates counterclockwise
+    let perpendicular = (x * stick.y - y * stick.x).clamp(-1.0, 1.0);
+    if perpendicular.abs() < STICK_DEADZONE {
+        return Some("none");
+    }
+    let (sin, cos) = (MAX_DI_DEGREES * perpendicular).to_radians().sin_cos();
+    let (di_x, di_y) = (x * cos - y * sin, x * sin + y * cos);
+    
+    let elevation = |x: f32, y: f32| y.atan2(x.abs()).to_degrees();
+    if y > 0.0 && (elevation(di_x, di_y) - 45.0).abs() < (elevation(x, y) - 45.0).abs() {
+        return Some("survival");
+    }
+    Some(if di_x.abs() < x.abs() { "in" } else { "out" })
+}
+
+// A new button press, or a stick moved into a different non-neutral region
+fn is_new_action(prev: ControllerInputs, current: ControllerInputs) -> bool {
+    let pressed = current.buttons & !prev.buttons != 0;
+    let joystick = current.joystick != 0 && current.joystick != prev.joystick;
+    let cstick = current.cstick != 0 && current.cstick != prev.cstick;
+    pressed || joystick || cstick
+}
+
+fn is_in_hitstun(state: u16) -> bool {
+    state == action_states::DAMAGE_FALL || (action_states::DAMAGE_HI_1..=action_states::DAMAGE_FLY_ROLL).contains(&state)
+}
+
+// Accumulate percent increases as damage taken, crediting them as damage dealt
+// to the port that last hit the player. Drops (respawns) are ignored. A hit on
+// a player who has been out of hitstun long enough is an opening for the attacker.
+// Returns the attacker of a hit landed this frame, the lowest port if several were.
+fn track_damage(frame: &transpose::Frame, prev_percents: &mut HashMap<u8, f32>, frames_out_of_hitstun: &mut HashMap<u8, u32>, player_moves: &mut [PlayerMoveData]) -> Option<u8> {
+    let mut hit_by = None;
+    for port_data in &frame.ports {
+        let port: u8 = port_data.port.into();
+        let post = &port_data.leader.post;
+        
+        let out_of_hitstun = frames_out_of_hitstun.get(&port).copied().unwrap_or(OPENING_RESET_FRAMES);
+        let now_out_of_hitstun = if is_in_hitstun(post.state) { 0 } else { out_of_hitstun.saturating_add(1) };
+        frames_out_of_hitstun.insert(port, now_out_of_hitstun);
+        
+        let Some(prev_percent) = prev_percents.insert(port, post.percent) else {
+            continue;
+        };
+        
+        let delta = post.percent - prev_percent;
+        if delta <= 0.0 {
+            continue;
+        }
+        
+        if let Some(victim) = player_moves.iter_mut().find(|p| p.port == port) {
+            victim.damage_taken += delta;
+        }
+        if post.last_hit_by != port {
+            if let Some(attacker) = player_moves.iter_mut().find(|p| p.port == post.last_hit_by) {
+                attacker.damage_dealt += delta;
+                if out_of_hitstun >= OPENING_RESET_FRAMES {
+                    attacker.openings_won += 1;
+                }
+                hit_by = Some(hit_by.map_or(attacker.port, |port: u8| port.min(attacker.port)));
+            }
+        }
+    }
+    hit_by
+}
+
+// A grabber who dies within this many frames of the opponent they were holding
+// (Falcon's or Ganondorf's side-B taken offstage) traded stocks rather than
+// self-destructing
+const SUICIDE_GRAB_WINDOW: u32 = 10;
+
+#[derive(Default)]
+struct DeathTracker {
+    // Stocks, percent and whether the port was held, as of the previous frame
+    prev_lives: HashMap<u8, (u8, f32, bool)>,
+    // Frames since each port was last in hitstun or held by an opponent
+    frames_unhurt: HashMap<u8, u32>,
+    // Frame of each port's latest death while held, and latest self-destruct
+    held_deaths: HashMap<u8, u32>,
+    self_destructs: HashMap<u8, u32>,
+}
+
+// States the player can act out of on their next frame: standing, walking,
+// dashing and running, jumping and falling (but not helpless), crouching,
+// holding shield and hanging on the ledge. Everything else, from hitstun and
+// landing lag to attacks, rolls and jumpsquat, is non-actionable.
+fn is_actionable(state: u16) -> bool {
+    use action_states::*;
+    (WAIT..=RUN_BRAKE).contains(&state)
+        || (JUMP_F..=FALL_AERIAL_B).contains(&state)
+        || (SQUAT..=SQUAT_RV).contains(&state)
+        || state == GUARD
+        || state == CLIFF_WAIT
+}
+
+fn is_held(state: u16) -> bool {
+    use action_states::*;
+    (CAPTURE_PULLED_HI..=CAPTURE_FOOT).contains(&state)
+        || (THROWN_F..=THROWN_LW_WOMEN).contains(&state)
+        || (COMMAND_GRABBED_START..=COMMAND_GRABBED_END).contains(&state)
+        || (COMMAND_GRABBED_2_START..=COMMAND_GRABBED_2_END).contains(&state)
+}
+
+// Record a death whenever a port's stock count drops, at the percent it had on
+// the frame before (the stock is lost on the frame the percent resets). Dying
+// without having been hit or held for an opening's worth of frames is a
+// self-destruct, unless another player died held within SUICIDE_GRAB_WINDOW
+// frames of it (whichever went first).
+fn track_deaths(frame: &transpose::Frame, frame_idx: u32, tracker: &mut DeathTracker, player_moves: &mut [PlayerMoveData]) {
+    let mut died: Vec<(u8, f32, bool)> = Vec::new();
+    for port_data in &frame.ports {
+        let port: u8 = port_data.port.into();
+        let post = &port_data.leader.post;
+        let held = is_held(post.state);
+        let frames_unhurt = tracker.frames_unhurt.entry(port).or_insert(OPENING_RESET_FRAMES);
+        // Judged on the frames before the stock was lost
+        let was_unhurt = *frames_unhurt >= OPENING_RESET_FRAMES;
+        *frames_unhurt = if held || is_in_hitstun(post.state) { 0 } else { frames_unhurt.saturating_add(1) };
+        
+        let Some((prev_stocks, prev_percent, was_held)) = tracker.prev_lives.insert(port, (post.stocks, post.percent, held)) else {
+            continue;
+        };
+        if post.stocks < prev_stocks {
+            died.push((port, prev_percent, was_unhurt));
+            if was_held {
+                tracker.held_deaths.insert(port, frame_idx);
+            }
+        }
+    }
+    
+    let recent = |frame: u32| frame + SUICIDE_GRAB_WINDOW >= frame_idx;
+    for (port, percent, unhurt) in died {
+        let traded = tracker.held_deaths.iter().any(|(&other, &frame)| other != port && recent(frame));
+        let self_destruct = unhurt && !traded;
+        if self_destruct {
+            tracker.self_destructs.insert(port, frame_idx);
+        }
+        if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == port) {
+            player_data.deaths.push(Death { percent, self_destruct });
+            if self_destruct {
+                player_data.self_destructs += 1;
+            }
+        }
+    }
+    
+    // A grabber who went first is cleared once the opponent they held dies
+    let held_now: Vec<u8> = tracker.held_deaths.iter().filter(|&(_, &frame)| frame == frame_idx).map(|(&port, _)| port).collect();
+    if held_now.is_empty() {
+        return;
+    }
+    let cleared: Vec<u8> = tracker.self_destructs
+        .iter()
+        .filter(|&(port, &frame)| !held_now.contains(port) && frame != frame_idx && recent(frame))
+        .map(|(&port, _)| port)
+        .collect();
+    for port in cleared {
+        tracker.self_destructs.remove(&port);
+        if let Some(player_data) = player_moves.iter_mut().find(|p| p.port == port) {
+            if let Some(death) = player_data.deaths.last_mut() {
+                death.self_destruct = false;
+                player_data.self_destructs -= 1;
+            }
+        }
+    }
+}
+
+// Analyze a single frame for move detection
+fn analyze_frame_for_moves(data: &transpose::Data, prev_state: Option<u16>, prev_inputs: Option<ControllerInputs>, player_data: &mut PlayerMoveData, frame_idx: usize, version: Version) {
+    // Count actions for APM
+    player_data.frames_played += 1;
+    if is_new_action(prev_inputs.unwrap_or_default(), controller_inputs(&data.pre)) {
+        player_data.actions += 1;
+    }
+    
+    // Hurtbox state 1 is invulnerable, 2 intangible
+    if matches!(data.post.hurtbox_state, Some(1 | 2)) {
+        player_data.invincible_frames += 1;
+    }
+    if is_actionable(data.pre.state) {
+        player_data.actionable_frames += 1;
+    }
+    
+    // Get action state
+    let action_state = data.pre.state;
+    let character = player_data.character.as_str();
+    
+    // Identify moves based on action state, counting only the transition into the move
+    if let Some(move_name) = identify_move_from_action_state(action_state, character) {
+        let prev_move = prev_state.and_then(|state| identify_move_from_action_state(state, character));
+        if prev_move.as_ref() != Some(&move_name) {
+            count_move(player_data, &move_name, data.post.stocks);
+        }
+    }
+    
+    analyze_shield(data, prev_state, player_data);
+    
+    // Additional analysis for special moves and techniques
+    analyze_special_techniques(data, prev_state, player_data, frame_idx, version);
+}
+
+// Character-specific special moves as (first state, last state, move name).
+// Falco's specials share Fox's state layout but get their own names.
+const FOX_SPECIALS: &[(u16, u16, &str)] = &[
+    (0x155, 0x15A, "laser"),
+    (0x15B, 0x160, "illusion"),
+    (0x161, 0x166, "firefox"),
+    (0x167, 0x170, "shine"),
+];
+
+const FALCO_SPECIALS: &[(u16, u16, &str)] = &[
+    (0x155, 0x15A, "laser"),
+    (0x15B, 0x160, "phantasm"),
+    (0x161, 0x166, "firebird"),
+    (0x167, 0x170, "shine"),
+];
+
+const MARTH_SPECIALS: &[(u16, u16, &str)] = &[
+    (0x155, 0x15C, "shield_breaker"),
+    (0x15D, 0x16E, "dancing_blade"),
+    (0x16F, 0x170, "dolphin_slash"),
+    (0x171, 0x174, "counter"),
+];
+
+// Characters without their own table get generic names. Specials follow the
+// neutral, side, up, down order for everyone, but how many states each takes
+// varies, so these ranges (Fox's) are only approximate for other characters.
+const GENERIC_SPECIALS: &[(u16, u16, &str)] = &[
+    (0x155, 0x15A, "neutral_b"),
+    (0x15B, 0x160, "side_b"),
+    (0x161, 0x166, "up_b"),
+    (0x167, 0x170, "down_b"),
+];
+
+fn special_moves_for(character: &str) -> &'static [(u16, u16, &'static str)] {
+    match character {
+        "Fox" => FOX_SPECIALS,
+        "Falco" => FALCO_SPECIALS,
+        "Marth" => MARTH_SPECIALS,
+        _ => GENERIC_SPECIALS,
+    }
+}
+
+// Map action states to move names
+pub fn identify_move_from_action_state(action_state: u16, character: &str) -> Option<String> {
+    use action_states::*;
+
+    if action_state >= SPECIAL_START {
+        return special_moves_for(character)
+            .iter()
+            .find(|(first, last, _)| (*first..=*last).contains(&action_state))
+            .map(|(_, _, name)| name.to_string());
+    }
+
+    let name = match action_state {
+        // Aerial attacks
+        ATTACK_AIR_N => "nair",
+        ATTACK_AIR_F => "fair",
+        ATTACK_AIR_B => "bair",
+        ATTACK_AIR_HI => "uair",
+        ATTACK_AIR_LW => "dair",
+        
+        // Ground attacks
+        ATTACK_11 | ATTACK_12 | ATTACK_13 => "jab",
+        ATTACK_100_START | ATTACK_100_LOOP | ATTACK_100_END => "rapid_jab",
+        ATTACK_S3_HI | ATTACK_S3_HI_S | ATTACK_S3_S | ATTACK_S3_LW_S | ATTACK_S3_LW => "ftilt",
+        ATTACK_HI3 => "utilt",
+        ATTACK_LW3 => "dtilt",
+        ATTACK_S4_HI | ATTACK_S4_HI_S | ATTACK_S4_S | ATTACK_S4_LW_S | ATTACK_S4_LW => "fsmash",
+        ATTACK_HI4 => "usmash",
+        ATTACK_LW4 => "dsmash",
+        ATTACK_DASH => "dash_attack",
+        
+        // Grabs
+        CATCH | CATCH_DASH => "grab",
+        
+        // Movement
+        JUMP_F | JUMP_B => "jump",
+        JUMP_AERIAL_F | JUMP_AERIAL_B => "double_jump",
+        
+        _ => return None,
+    };
+    
+    Some(name.to_string())
+}
+
+// Analyze special techniques like wavedash, L-cancel, etc.
+fn analyze_special_techniques(data: &transpose::Data, prev_state: Option<u16>, player_data: &mut PlayerMoveData, _frame_idx: usize, version: Version) {
+    // Techniques are keyed off entering a state, not every frame spent in it
+    if prev_state == Some(data.pre.state) {
+        return;
+    }
+    
+    // Check for wavedash (air dodge into ground within short timeframe).
+    // The airborne flag is only recorded from Slippi 2.0 onwards.
+    let grounded = if version.gte(2, 0) { data.post.airborne == Some(0) } else { true };
+    if data.pre.state == action_states::LANDING_FALL_SPECIAL && grounded { // Air dodge that ends on ground
+        count_move(player_data, "wavedash", data.post.stocks);
+    }
+    
+    analyze_tech_situations(data, player_data);
+    analyze_throws(data, prev_state, player_data);
+    
+    // Aerial landing lag. Auto-cancelled aerials land in the normal landing
+    // state instead, so they never reach here.
+    let landing_states = action_states::LANDING_AIR_N..=action_states::LANDING_AIR_LW;
+    if landing_states.contains(&data.pre.state) && !prev_state.is_some_and(|state| landing_states.contains(&state)) {
+        // From 2.0 the game's own verdict is recorded (1 = success, 2 = missed);
+        // older replays fall back to a trigger held on the landing frame
+        let cancelled = match data.post.l_cancel {
+            Some(l_cancel) => l_cancel == 1,
+            None => data.pre.buttons & 0x70 != 0, // L, R or Z
+        };
+        
+        player_data.aerial_landings += 1;
+        if cancelled {
+            count_move(player_data, "l_cancel", data.post.stocks);
+        }
+    }
+}
+
+// Count a throw when a player goes from holding an opponent (pulling them in,
+// holding or pummeling) into one of the four throws
+fn analyze_throws(data: &transpose::Data, prev_state: Option<u16>, player_data: &mut PlayerMoveData) {
+    use action_states::*;
+    
+    if !prev_state.is_some_and(|state| matches!(state, CATCH_PULL | CATCH_DASH_PULL | CATCH_WAIT | CATCH_ATTACK)) {
+        return;
+    }
+    let throw = match data.pre.state {
+        THROW_F => "fthrow",
+        THROW_B => "bthrow",
+        THROW_HI => "uthrow",
+        THROW_LW => "dthrow",
+        _ => return,
+    };
+    count_move(player_data, throw, data.post.stocks);
+}
+
+// Time spent holding shield, and what the player did instead: rolls and spot
+// dodges, dropping through a platform, or grabbing or jumping out of shield
+fn analyze_shield(data: &transpose::Data, prev_state: Option<u16>, player_data: &mut PlayerMoveData) {
+    use action_states::*;
+    
+    let in_shield = |state| matches!(state, GUARD_ON | GUARD | GUARD_SET_OFF | GUARD_REFLECT);
+    if in_shield(data.pre.state) {
+        player_data.shield_frames += 1;
+    }
+    if prev_state == Some(data.pre.state) {
+        return;
+    }
+    
+    let from_shield = prev_state.is_some_and(|state| in_shield(state) || state == GUARD_OFF);
+    let option = match data.pre.state {
+        ESCAPE_F => "roll_forward",
+        ESCAPE_B => "roll_back",
+        ESCAPE => "spot_dodge",
+        PASS if from_shield => "shield_drop",
+        CATCH if from_shield => "oos_grab",
+        KNEE_BEND if from_shield => "oos_jump",
+        _ => return,
+    };
+    count_move(player_data, option, data.post.stocks);
+}
+
+// Record how a player dealt with hitting the ground in tumble: teching in
+// place, tech rolling, or missing the tech and getting knocked down. Called
+// only on the frame a state is entered.
+fn analyze_tech_situations(leader: &transpose::Data, player_data: &mut PlayerMoveData) {
+    let tech = match leader.pre.state {
+        action_states::PASSIVE => "tech_in_place",
+        action_states::PASSIVE_STAND_F | action_states::PASSIVE_STAND_B => {
+            // Forward rolls go the way the player faces; direction is 1 for right, -1 for left
+            let forward = leader.pre.state == action_states::PASSIVE_STAND_F;
+            let rightward = (leader.post.direction > 0.0) == forward;
+            let side = if rightward { "tech_roll_right" } else { "tech_roll_left" };
+            count_move(player_data, side, leader.post.stocks);
+            "tech_roll"
+        }
+        action_states::DOWN_BOUND_U | action_states::DOWN_BOUND_D => "missed_tech",
+        _ => return,
+    };
+    
+    count_move(player_data, tech, leader.post.stocks);
+}
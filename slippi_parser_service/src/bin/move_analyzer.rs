@@ -1,10 +1,22 @@
 use anyhow::Result;
 use clap::Parser;
+use rayon::prelude::*;
+use chrono::NaiveDate;
 use std::path::PathBuf;
-use std::fs;
-use std::collections::HashMap;
-use serde::{Deserialize, Serialize};
-use tracing::{info, error};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::sync::{mpsc, Arc};
+use std::collections::{BTreeMap, HashMap};
+use serde::Serialize;
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt32Array, UInt8Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use tracing::{info, warn, error};
+use slippi_parser_service::{collect_game_files, generate_csv_output, load_filtered_game, load_games, process_directory_for_moves, process_directory_per_game, write_json, AnalysisConfig, GameData, GameFilter, GameResult, MoveAliases, MoveStats};
+use cli::{check_games_found, configure_jobs, init_logging, CsvLayoutArg, GroupByArg};
+
+#[path = "../cli.rs"]
+mod cli;
 
 #[derive(Parser)]
 #[command(name = "move_analyzer")]
@@ -14,68 +26,242 @@ struct Args {
     #[arg(short, long)]
     directory: PathBuf,
     
-    /// Output format (json, csv, text)
+    /// Output format (json, yaml, csv, text, markdown, prometheus, ndjson, sqlite, parquet; sqlite and parquet require --output)
     #[arg(long, default_value = "json")]
     format: String,
     
     /// Output file path (optional, defaults to stdout)
     #[arg(short, long)]
     output: Option<PathBuf>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct GameData {
-    player_count: usize,
-    duration_frames: u32,
-    stage: String,
-    players: Vec<PlayerData>,
-    moves: Option<Vec<PlayerMoveData>>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct PlayerData {
-    port: u8,
-    character: String,
-    stocks: u8,
-    costume: u8,
-    team: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct PlayerMoveData {
-    port: u8,
-    character: String,
-    moves: HashMap<String, u32>,
+    
+    /// Also process JSON files in subdirectories (e.g. month folders)
+    #[arg(long)]
+    recursive: bool,
+    
+    /// Abort on the first file that fails to parse instead of skipping it
+    #[arg(long)]
+    strict: bool,
+    
+    /// Exit with an error when no games were processed, e.g. because the directory path is wrong
+    #[arg(long)]
+    fail_on_empty: bool,
+    
+    /// Threads used to process files in parallel (default: one per logical core)
+    #[arg(long)]
+    jobs: Option<usize>,
+    
+    /// Combine each player's games into a single entry
+    #[arg(long, value_enum)]
+    group_by: Option<GroupByArg>,
+    
+    /// Leave out players seen in fewer than this many games, from the player list and the aggregates
+    #[arg(long, default_value_t = 0)]
+    min_games: u32,
+    
+    /// Only include games where someone plays this character (case and spacing are ignored)
+    #[arg(long)]
+    character: Option<String>,
+    
+    /// Only report this port's player. Ports are 0-based, as in the output's `port` field: P1 is 0, P4 is 3
+    #[arg(long)]
+    port: Option<u8>,
+    
+    /// Only include games on this stage; repeat to allow several
+    #[arg(long = "stage")]
+    stages: Vec<String>,
+    
+    /// Only include games played on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    
+    /// Only include games played on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    
+    /// Only include games of this match type
+    #[arg(long, value_parser = ["ranked", "unranked", "direct", "offline"])]
+    match_type: Option<String>,
+    
+    /// Include games marked incomplete (under a second of play), which are skipped by default
+    #[arg(long)]
+    include_incomplete: bool,
+    
+    /// Skip byte-identical copies of the same game file
+    #[arg(long)]
+    dedup: bool,
+    
+    /// Number of moves listed per player in text output (0 lists them all)
+    #[arg(long, default_value_t = 5)]
+    top: usize,
+    
+    /// CSV layout: one row per move (long) or one column per move (wide)
+    #[arg(long, value_enum, default_value = "long")]
+    csv_layout: CsvLayoutArg,
+    
+    /// Add moves_per_stock: each move's count divided by the stocks the player used
+    #[arg(long)]
+    per_stock: bool,
+    
+    /// Output one JSON entry per game instead of aggregated statistics
+    #[arg(long)]
+    per_game: bool,
+    
+    /// Output head-to-head statistics for each pair of connect codes as JSON
+    #[arg(long)]
+    matchups: bool,
+    
+    /// Leave the per-player entries out of JSON or YAML output, keeping only the totals
+    #[arg(long)]
+    summary_only: bool,
+    
+    /// JSON file mapping move names to the names to print instead, e.g. {"nair": "neutral_air"}
+    #[arg(long)]
+    alias_file: Option<PathBuf>,
+    
+    /// Write JSON on a single line without indentation
+    #[arg(long, overrides_with = "pretty")]
+    compact: bool,
+    
+    /// Indent JSON output (the default; overrides an earlier --compact)
+    #[arg(long, overrides_with = "compact")]
+    pretty: bool,
+    
+    /// Log progress with -v, debug output with -vv (default: errors only; a plain RUST_LOG level is used when neither -v nor -q is given)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    
+    /// Don't log anything, not even errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
 }
 
 #[derive(Serialize)]
-struct MoveStats {
-    total_games: u32,
-    players: Vec<PlayerMoveData>,
-    aggregated_stats: HashMap<String, serde_json::Value>,
+struct MatchupStats {
+    player_a: String,
+    player_b: String,
+    games_played: u32,
+    player_a_wins: u32,
+    player_b_wins: u32,
+    // "<player_a character> vs <player_b character>" -> games
+    character_pairings: HashMap<String, u32>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-    
     let args = Args::parse();
+    init_logging(args.verbose, args.quiet);
+    configure_jobs(args.jobs)?;
+    let aliases = args.alias_file.as_deref().map(MoveAliases::load).transpose()?.unwrap_or_default();
     
     info!("Starting move analyzer");
     info!("Processing directory: {:?}", args.directory);
+    let config = AnalysisConfig::builder()
+        .recursive(args.recursive)
+        .strict(args.strict)
+        .group_by(args.group_by.map(Into::into))
+        .min_games(args.min_games)
+        .filter(GameFilter {
+            character: args.character.clone(),
+            port: args.port,
+            stages: args.stages.clone(),
+            since: args.since,
+            until: args.until,
+            match_type: args.match_type.clone(),
+            include_incomplete: args.include_incomplete,
+            dedup: args.dedup,
+        })
+        .build();
+    
+    if args.per_game {
+        let mut games = process_directory_per_game(&args.directory, &config)?;
+        check_games_found(games.len(), args.fail_on_empty)?;
+        for player_moves in games.iter_mut().flat_map(|game| &mut game.moves) {
+            if args.per_stock {
+                player_moves.moves_per_stock = player_moves.compute_moves_per_stock();
+            }
+            aliases.rename_player(player_moves);
+        }
+        return write_json_output(args.output, !args.compact, &games);
+    }
+    
+    if args.matchups {
+        let games: Vec<GameData> = load_games(&args.directory, config.recursive, config.strict, &config.filter)?
+            .into_iter()
+            .map(|(_, game_data)| game_data)
+            .collect();
+        check_games_found(games.len(), args.fail_on_empty)?;
+        return write_json_output(args.output, !args.compact, &compute_matchups(&games));
+    }
+    
+    if args.format == "ndjson" {
+        // Stream one game per line rather than building the full stats in memory
+        let written = match &args.output {
+            Some(output_path) => write_ndjson(&args.directory, config.recursive, config.strict, &config.filter, &aliases, &mut BufWriter::new(File::create(output_path)?))?,
+            None => write_ndjson(&args.directory, config.recursive, config.strict, &config.filter, &aliases, &mut io::stdout().lock())?,
+        };
+        info!("Streamed {} games", written);
+        check_games_found(written, args.fail_on_empty)?;
+        return Ok(());
+    }
+    
+    if args.format == "sqlite" {
+        let Some(output_path) = args.output else {
+            return Err(anyhow::anyhow!("--format sqlite requires --output"));
+        };
+        let mut games = load_games(&args.directory, config.recursive, config.strict, &config.filter)?;
+        check_games_found(games.len(), args.fail_on_empty)?;
+        rename_game_moves(&aliases, &mut games);
+        let mut conn = rusqlite::Connection::open(&output_path)?;
+        write_sqlite(&mut conn, &games)?;
+        info!("Wrote {} games to {:?}", games.len(), output_path);
+        return Ok(());
+    }
+    
+    if args.format == "parquet" {
+        let Some(output_path) = args.output else {
+            return Err(anyhow::anyhow!("--format parquet requires --output"));
+        };
+        let mut games = load_games(&args.directory, config.recursive, config.strict, &config.filter)?;
+        check_games_found(games.len(), args.fail_on_empty)?;
+        rename_game_moves(&aliases, &mut games);
+        let rows = write_parquet(&games, File::create(&output_path)?)?;
+        info!("Wrote {} move rows to {:?}", rows, output_path);
+        return Ok(());
+    }
     
-    match process_directory_for_moves(&args.directory).await {
-        Ok(stats) => {
+    match process_directory_for_moves(&args.directory, &config).await {
+        Ok(mut stats) => {
+            check_games_found(stats.total_games as usize, args.fail_on_empty)?;
+            if args.per_stock {
+                for player_moves in stats.players.iter_mut().flatten() {
+                    player_moves.moves_per_stock = player_moves.compute_moves_per_stock();
+                }
+            }
+            aliases.rename_stats(&mut stats);
             let output = match args.format.as_str() {
                 "json" => {
-                    serde_json::to_string_pretty(&stats)?
+                    if args.summary_only {
+                        stats.summarize();
+                    }
+                    return write_json_output(args.output, !args.compact, &stats);
+                }
+                "yaml" => {
+                    if args.summary_only {
+                        stats.summarize();
+                    }
+                    serde_yaml::to_string(&stats)?
                 }
                 "csv" => {
-                    generate_csv_output(&stats)?
+                    generate_csv_output(stats.players(), args.csv_layout.into())
                 }
                 "text" => {
-                    generate_text_output(&stats)
+                    generate_text_output(&stats, args.top)
+                }
+                "markdown" => {
+                    generate_markdown_output(&stats)
+                }
+                "prometheus" => {
+                    generate_prometheus_output(&stats)
                 }
                 _ => {
                     error!("Unknown format: {}", args.format);
@@ -83,13 +269,7 @@ async fn main() -> Result<()> {
                 }
             };
             
-            // Output to file or stdout
-            if let Some(output_path) = args.output {
-                fs::write(output_path, output)?;
-                info!("Output saved to file");
-            } else {
-                println!("{}", output);
-            }
+            write_output(args.output, output)?;
         }
         Err(e) => {
             error!("Failed to process directory: {}", e);
@@ -100,97 +280,606 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn process_directory_for_moves(directory: &PathBuf) -> Result<MoveStats> {
-    let mut total_games = 0;
-    let mut all_players: Vec<PlayerMoveData> = Vec::new();
-    let mut aggregated_moves: HashMap<String, u32> = HashMap::new();
-    
-    // Read all JSON files in the directory
-    for entry in fs::read_dir(directory)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.extension().map_or(false, |ext| ext == "json") {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(game_data) = serde_json::from_str::<GameData>(&content) {
-                    total_games += 1;
-                    
-                    if let Some(moves) = game_data.moves {
-                        for player_moves in moves {
-                            // Aggregate moves
-                            for (move_name, count) in &player_moves.moves {
-                                let total_count = aggregated_moves.entry(move_name.clone()).or_insert(0);
-                                *total_count += count;
-                            }
-                            
-                            // Store player data
-                            all_players.push(player_moves);
-                        }
+// Output to file or stdout
+fn write_output(output_path: Option<PathBuf>, output: String) -> Result<()> {
+    if let Some(output_path) = output_path {
+        fs::write(output_path, output)?;
+        info!("Output saved to file");
+    } else {
+        println!("{}", output);
+    }
+    
+    Ok(())
+}
+
+// Apply --alias-file to games loaded for the table outputs
+fn rename_game_moves(aliases: &MoveAliases, games: &mut [(PathBuf, GameData)]) {
+    for player_moves in games.iter_mut().flat_map(|(_, game_data)| game_data.moves.iter_mut().flatten()) {
+        aliases.rename_player(player_moves);
+    }
+}
+
+// Stream JSON to the output file or stdout without building it as a String
+fn write_json_output<T: Serialize>(output_path: Option<PathBuf>, pretty: bool, value: &T) -> Result<()> {
+    if let Some(output_path) = output_path {
+        write_json(File::create(output_path)?, value, pretty)?;
+        info!("Output saved to file");
+    } else {
+        write_json(io::stdout().lock(), value, pretty)?;
+    }
+    
+    Ok(())
+}
+
+// Parse games in parallel and write each one as a line of JSON as soon as it is
+// ready, flushing per line. The bounded channel keeps memory flat however many
+// files there are. Returns the number of games written.
+fn write_ndjson<W: Write>(directory: &PathBuf, recursive: bool, strict: bool, filter: &GameFilter, aliases: &MoveAliases, writer: &mut W) -> Result<usize> {
+    let (paths, _) = collect_game_files(directory, recursive, filter)?;
+    let (sender, receiver) = mpsc::sync_channel::<(PathBuf, Result<GameData>)>(64);
+    
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            paths.par_iter().for_each_with(sender, |sender, path| {
+                if let Some(result) = load_filtered_game(path, filter, None) {
+                    // A closed channel means the writer gave up; nothing left to do
+                    let _ = sender.send((path.clone(), result));
+                }
+            });
+        });
+        
+        let mut written = 0;
+        for (path, result) in receiver {
+            match result {
+                Ok(mut game_data) => {
+                    for player_moves in game_data.moves.iter_mut().flatten() {
+                        aliases.rename_player(player_moves);
                     }
+                    serde_json::to_writer(&mut *writer, &GameResult::new(path, game_data))?;
+                    writer.write_all(b"\n")?;
+                    writer.flush()?;
+                    written += 1;
                 }
+                Err(e) if strict => return Err(anyhow::anyhow!("Failed to parse {:?}: {}", path, e)),
+                Err(e) => warn!("Skipping {:?}: {}", path, e),
             }
         }
-    }
+        
+        Ok(written)
+    })
+}
+
+// Head-to-head statistics for every pair of connect codes that met. Games with
+// fewer than two named players are skipped; dittos count like any other pairing.
+// In doubles only players on opposing teams are paired, and a win goes to
+// everyone on the winning team.
+fn compute_matchups(games: &[GameData]) -> Vec<MatchupStats> {
+    let mut matchups: BTreeMap<(String, String), MatchupStats> = BTreeMap::new();
     
-    // Create aggregated statistics
-    let mut stats_map = HashMap::new();
-    if let Some(most_common) = aggregated_moves.iter().max_by_key(|(_, count)| *count) {
-        stats_map.insert("most_common_move".to_string(), serde_json::Value::String(most_common.0.clone()));
+    for game in games {
+        let named: Vec<(&str, &str, u8)> = game.players
+            .iter()
+            .filter_map(|p| p.connect_code.as_deref().map(|code| (code, p.character.as_str(), p.port)))
+            .collect();
+        
+        let team_of = |port: u8| game.players.iter().find(|p| p.port == port).and_then(|p| p.team.as_deref());
+        
+        for (i, &first) in named.iter().enumerate() {
+            for &second in &named[i + 1..] {
+                if first.0 == second.0 {
+                    continue;
+                }
+                // Teammates aren't opponents
+                if team_of(first.2).is_some() && team_of(first.2) == team_of(second.2) {
+                    continue;
+                }
+                
+                // Order each pair by connect code so A-vs-B and B-vs-A land together
+                let ((code_a, character_a, port_a), (code_b, character_b, port_b)) = if first.0 < second.0 {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                
+                let matchup = matchups
+                    .entry((code_a.to_string(), code_b.to_string()))
+                    .or_insert_with(|| MatchupStats {
+                        player_a: code_a.to_string(),
+                        player_b: code_b.to_string(),
+                        games_played: 0,
+                        player_a_wins: 0,
+                        player_b_wins: 0,
+                        character_pairings: HashMap::new(),
+                    });
+                matchup.games_played += 1;
+                let won = |port: u8| game.winner == Some(port)
+                    || game.winning_team.is_some() && game.winning_team.as_deref() == team_of(port);
+                if won(port_a) {
+                    matchup.player_a_wins += 1;
+                } else if won(port_b) {
+                    matchup.player_b_wins += 1;
+                }
+                *matchup.character_pairings.entry(format!("{} vs {}", character_a, character_b)).or_insert(0) += 1;
+            }
+        }
     }
     
-    let total_moves: u32 = aggregated_moves.values().sum();
-    let avg_moves_per_game = if total_games > 0 { total_moves / total_games } else { 0 };
-    stats_map.insert("average_moves_per_game".to_string(), serde_json::Value::Number(avg_moves_per_game.into()));
-    
-    Ok(MoveStats {
-        total_games,
-        players: all_players,
-        aggregated_stats: stats_map,
-    })
+    matchups.into_values().collect()
 }
 
-fn generate_csv_output(stats: &MoveStats) -> Result<String> {
-    let mut output = String::new();
-    output.push_str("port,character,move,count\n");
+// Write games, their players and each player's move counts into SQLite tables
+// linked by foreign keys, all in one transaction
+fn write_sqlite(conn: &mut rusqlite::Connection, games: &[(PathBuf, GameData)]) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS games (
+            id INTEGER PRIMARY KEY,
+            file TEXT NOT NULL,
+            stage TEXT NOT NULL,
+            duration_frames INTEGER NOT NULL,
+            winner INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS players (
+            id INTEGER PRIMARY KEY,
+            game_id INTEGER NOT NULL REFERENCES games(id),
+            port INTEGER NOT NULL,
+            character TEXT NOT NULL,
+            display_name TEXT,
+            connect_code TEXT
+        );
+        CREATE TABLE IF NOT EXISTS moves (
+            player_id INTEGER NOT NULL REFERENCES players(id),
+            move TEXT NOT NULL,
+            count INTEGER NOT NULL
+        );",
+    )?;
     
-    for player in &stats.players {
-        for (move_name, count) in &player.moves {
-            output.push_str(&format!("{},{},{},{}\n", player.port, player.character, move_name, count));
+    let tx = conn.transaction()?;
+    {
+        let mut insert_game = tx.prepare("INSERT INTO games (file, stage, duration_frames, winner) VALUES (?1, ?2, ?3, ?4)")?;
+        let mut insert_player = tx.prepare("INSERT INTO players (game_id, port, character, display_name, connect_code) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        let mut insert_move = tx.prepare("INSERT INTO moves (player_id, move, count) VALUES (?1, ?2, ?3)")?;
+        
+        for (path, game_data) in games {
+            insert_game.execute(rusqlite::params![path.to_string_lossy(), game_data.stage, game_data.duration_frames, game_data.winner])?;
+            let game_id = tx.last_insert_rowid();
+            
+            for player_moves in game_data.moves.iter().flatten() {
+                // Older files only carry names on the players list
+                let player = game_data.players.iter().find(|p| p.port == player_moves.port);
+                let display_name = player_moves.display_name.as_ref().or(player.and_then(|p| p.display_name.as_ref()));
+                let connect_code = player_moves.connect_code.as_ref().or(player.and_then(|p| p.connect_code.as_ref()));
+                
+                insert_player.execute(rusqlite::params![game_id, player_moves.port, player_moves.character, display_name, connect_code])?;
+                let player_id = tx.last_insert_rowid();
+                
+                for (move_name, count) in &player_moves.moves {
+                    insert_move.execute(rusqlite::params![player_id, move_name, count])?;
+                }
+            }
         }
     }
+    tx.commit()?;
     
-    Ok(output)
+    Ok(())
+}
+
+// Write the long-format move table (game_file, port, character, move, count),
+// one row per move per player per game, as a single Parquet row group.
+// Returns the number of rows written.
+fn write_parquet<W: Write + Send>(games: &[(PathBuf, GameData)], writer: W) -> Result<usize> {
+    let mut game_files = Vec::new();
+    let mut ports = Vec::new();
+    let mut characters = Vec::new();
+    let mut move_names = Vec::new();
+    let mut counts = Vec::new();
+    for (path, game_data) in games {
+        for player_moves in game_data.moves.iter().flatten() {
+            let mut moves: Vec<_> = player_moves.moves.iter().collect();
+            moves.sort();
+            for (move_name, count) in moves {
+                game_files.push(path.to_string_lossy().into_owned());
+                ports.push(player_moves.port);
+                characters.push(player_moves.character.clone());
+                move_names.push(move_name.clone());
+                counts.push(*count);
+            }
+        }
+    }
+    
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("game_file", DataType::Utf8, false),
+        Field::new("port", DataType::UInt8, false),
+        Field::new("character", DataType::Utf8, false),
+        Field::new("move", DataType::Utf8, false),
+        Field::new("count", DataType::UInt32, false),
+    ]));
+    let batch = RecordBatch::try_new(schema.clone(), vec![
+        Arc::new(StringArray::from(game_files)) as ArrayRef,
+        Arc::new(UInt8Array::from(ports)),
+        Arc::new(StringArray::from(characters)),
+        Arc::new(StringArray::from(move_names)),
+        Arc::new(UInt32Array::from(counts)),
+    ])?;
+    
+    let mut parquet_writer = ArrowWriter::try_new(writer, schema, None)?;
+    parquet_writer.write(&batch)?;
+    parquet_writer.close()?;
+    
+    Ok(batch.num_rows())
 }
 
-fn generate_text_output(stats: &MoveStats) -> String {
+fn generate_text_output(stats: &MoveStats, top: usize) -> String {
     let mut output = String::new();
-    output.push_str(&format!("Move Statistics Summary\n"));
-    output.push_str(&format!("======================\n"));
+    output.push_str("Move Statistics Summary\n");
+    output.push_str("======================\n");
     output.push_str(&format!("Total games processed: {}\n", stats.total_games));
-    output.push_str(&format!("Total players analyzed: {}\n", stats.players.len()));
-    output.push_str(&format!("\n"));
+    output.push_str(&format!("Total players analyzed: {}\n", stats.players().len()));
+    output.push('\n');
     
     // Show aggregated stats
     if let Some(most_common) = stats.aggregated_stats.get("most_common_move") {
         output.push_str(&format!("Most common move: {}\n", most_common.as_str().unwrap_or("unknown")));
     }
     if let Some(avg_moves) = stats.aggregated_stats.get("average_moves_per_game") {
-        output.push_str(&format!("Average moves per game: {}\n", avg_moves.as_u64().unwrap_or(0)));
+        output.push_str(&format!("Average moves per game: {:.1}\n", avg_moves.as_f64().unwrap_or(0.0)));
+    }
+    
+    if !stats.teams.is_empty() {
+        output.push_str("\nTeam breakdown:\n");
+        for team in &stats.teams {
+            let total_moves: u32 = team.moves.values().sum();
+            output.push_str(&format!("{} ({}): {} total moves\n", team.team, team.players.join(", "), total_moves));
+        }
     }
     
-    output.push_str(&format!("\nPlayer breakdown:\n"));
-    for player in &stats.players {
+    output.push_str("\nPlayer breakdown:\n");
+    for player in stats.players() {
         let total_moves: u32 = player.moves.values().sum();
         output.push_str(&format!("Port {}: {} - {} total moves\n", player.port, player.character, total_moves));
         
-        // Show top 5 moves for each player
+        // Show each player's top moves
         let mut moves_vec: Vec<_> = player.moves.iter().collect();
         moves_vec.sort_by(|a, b| b.1.cmp(a.1));
-        for (i, (move_name, count)) in moves_vec.iter().take(5).enumerate() {
-            output.push_str(&format!("  {}. {}: {}\n", i + 1, move_name, count));
+        let shown = if top == 0 { moves_vec.len() } else { top };
+        for (i, (move_name, count)) in moves_vec.iter().take(shown).enumerate() {
+            let percentage = player.move_percentages.get(*move_name).copied().unwrap_or(0.0);
+            match player.move_outcomes.get(*move_name) {
+                Some(outcome) => output.push_str(&format!("  {}. {}: {} ({:.1}%, {} hit / {} whiff)\n", i + 1, move_name, count, percentage, outcome.hits, outcome.whiffs)),
+                None => output.push_str(&format!("  {}. {}: {} ({:.1}%)\n", i + 1, move_name, count, percentage)),
+            }
+        }
+        output.push('\n');
+    }
+    
+    if stats.duplicates_skipped > 0 {
+        output.push_str(&format!("Skipped {} duplicate files\n", stats.duplicates_skipped));
+    }
+    if !stats.failed_files.is_empty() {
+        output.push_str(&format!("Skipped {} files:\n", stats.failed_files.len()));
+        for failed in &stats.failed_files {
+            output.push_str(&format!("  {}: {}\n", failed.path.display(), failed.error));
         }
-        output.push_str(&format!("\n"));
     }
     
     output
-}
\ No newline at end of file
+}
+
+// Same content as the text summary, as GitHub-flavored Markdown tables
+fn generate_markdown_output(stats: &MoveStats) -> String {
+    let mut output = String::new();
+    output.push_str("## Move Statistics Summary\n\n");
+    output.push_str("| Stat | Value |\n");
+    output.push_str("|---|---|\n");
+    output.push_str(&format!("| Total games processed | {} |\n", stats.total_games));
+    output.push_str(&format!("| Total players analyzed | {} |\n", stats.players().len()));
+    if let Some(most_common) = stats.aggregated_stats.get("most_common_move") {
+        output.push_str(&format!("| Most common move | {} |\n", most_common.as_str().unwrap_or("unknown")));
+    }
+    if let Some(avg_moves) = stats.aggregated_stats.get("average_moves_per_game") {
+        output.push_str(&format!("| Average moves per game | {:.1} |\n", avg_moves.as_f64().unwrap_or(0.0)));
+    }
+    
+    output.push_str("\n## Player Breakdown\n\n");
+    output.push_str("| Port | Character | Total moves | Top moves |\n");
+    output.push_str("|---|---|---|---|\n");
+    for player in stats.players() {
+        let total_moves: u32 = player.moves.values().sum();
+        
+        // Top 5 moves, as in the text summary
+        let mut moves_vec: Vec<_> = player.moves.iter().collect();
+        moves_vec.sort_by(|a, b| b.1.cmp(a.1));
+        let top_moves: Vec<String> = moves_vec.iter().take(5).map(|(move_name, count)| format!("{} ({})", move_name, count)).collect();
+        
+        output.push_str(&format!("| {} | {} | {} | {} |\n", player.port, player.character, total_moves, top_moves.join(", ")));
+    }
+    
+    if !stats.failed_files.is_empty() {
+        output.push_str(&format!("\n## Skipped {} files\n\n", stats.failed_files.len()));
+        output.push_str("| File | Error |\n");
+        output.push_str("|---|---|\n");
+        for failed in &stats.failed_files {
+            output.push_str(&format!("| {} | {} |\n", failed.path.display(), failed.error.replace('|', "\\|")));
+        }
+    }
+    
+    output
+}
+
+// Prometheus text exposition format, for scraping into dashboards. Move
+// counts are summed per character, since a series can only appear once.
+fn generate_prometheus_output(stats: &MoveStats) -> String {
+    let mut move_counts: BTreeMap<(&str, &str), u32> = BTreeMap::new();
+    for player in stats.players() {
+        for (move_name, count) in &player.moves {
+            *move_counts.entry((player.character.as_str(), move_name.as_str())).or_insert(0) += count;
+        }
+    }
+    
+    let mut output = String::new();
+    output.push_str("# HELP shdl_games_total Games included in the statistics.\n");
+    output.push_str("# TYPE shdl_games_total gauge\n");
+    output.push_str(&format!("shdl_games_total {}\n", stats.total_games));
+    output.push_str("# HELP shdl_move_count Times each character used each move.\n");
+    output.push_str("# TYPE shdl_move_count gauge\n");
+    for ((character, move_name), count) in move_counts {
+        output.push_str(&format!("shdl_move_count{{character=\"{}\",move=\"{}\"}} {}\n", prometheus_label(character), prometheus_label(move_name), count));
+    }
+    output
+}
+
+// Escape a label value: backslash, double quote and line feed are the only
+// characters that need it
+fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slippi_parser_service::{CsvLayout, PlayerData, PlayerMoveData};
+
+    fn test_stats(players: Vec<PlayerMoveData>) -> MoveStats {
+        MoveStats {
+            total_games: 1,
+            players: Some(players),
+            teams: vec![],
+            aggregated_stats: HashMap::new(),
+            ranked_moves: vec![],
+            character_profiles: HashMap::new(),
+            character_profiles_per_game: HashMap::new(),
+            failed_files: vec![],
+            duplicates_skipped: 0,
+        }
+    }
+
+    #[test]
+    fn test_wide_csv_fills_missing_moves() {
+        let stats = test_stats(vec![
+            PlayerMoveData {
+                port: 0,
+                character: "Fox".to_string(),
+                moves: HashMap::from([("shine".to_string(), 12), ("nair".to_string(), 4)]),
+                ..Default::default()
+            },
+            PlayerMoveData {
+                port: 1,
+                character: "Fox, Falco".to_string(),
+                moves: HashMap::from([("fair".to_string(), 9)]),
+                ..Default::default()
+            },
+        ]);
+        
+        let csv = generate_csv_output(stats.players(), CsvLayout::Wide);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "port,character,fair,nair,shine");
+        assert_eq!(lines[1], "0,Fox,0,4,12");
+        assert_eq!(lines[2], "1,\"Fox, Falco\",9,0,0");
+    }
+
+    #[test]
+    fn test_move_percentages() {
+        let mut player = PlayerMoveData {
+            port: 0,
+            character: "Marth".to_string(),
+            moves: HashMap::from([("nair".to_string(), 10), ("fair".to_string(), 10)]),
+            ..Default::default()
+        };
+        player.move_percentages = player.compute_move_percentages();
+        assert_eq!(player.move_percentages.get("nair"), Some(&50.0));
+        assert_eq!(player.move_percentages.get("fair"), Some(&50.0));
+        
+        let text = generate_text_output(&test_stats(vec![player]), 5);
+        assert!(text.contains(": 10 (50.0%)\n"));
+    }
+
+    #[test]
+    fn test_text_output_average_moves_keeps_fraction() {
+        let mut stats = test_stats(vec![]);
+        stats.aggregated_stats.insert("average_moves_per_game".to_string(), serde_json::Value::from(149.7));
+        assert!(generate_text_output(&stats, 5).contains("Average moves per game: 149.7\n"));
+    }
+
+    #[test]
+    fn test_text_output_top_moves() {
+        let stats = test_stats(vec![PlayerMoveData {
+            port: 0,
+            character: "Fox".to_string(),
+            moves: HashMap::from([("shine".to_string(), 12), ("nair".to_string(), 4), ("uair".to_string(), 3), ("jab".to_string(), 2), ("dtilt".to_string(), 1)]),
+            ..Default::default()
+        }]);
+        let move_lines = |text: &str| text.lines().filter(|line| line.starts_with("  ")).count();
+        
+        assert_eq!(move_lines(&generate_text_output(&stats, 3)), 3);
+        assert_eq!(move_lines(&generate_text_output(&stats, 0)), 5);
+    }
+
+    #[test]
+    fn test_prometheus_output() {
+        let mut stats = test_stats(vec![
+            PlayerMoveData { port: 0, character: "Fox".to_string(), moves: HashMap::from([("nair".to_string(), 100)]), ..Default::default() },
+            PlayerMoveData { port: 1, character: "Fox".to_string(), moves: HashMap::from([("nair".to_string(), 23)]), ..Default::default() },
+            PlayerMoveData { port: 2, character: "Mr. \"G\\W\"\nGame".to_string(), moves: HashMap::from([("bair".to_string(), 4)]), ..Default::default() },
+        ]);
+        stats.total_games = 2;
+        
+        let output = generate_prometheus_output(&stats);
+        let samples: Vec<&str> = output.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(samples, vec![
+            "shdl_games_total 2",
+            "shdl_move_count{character=\"Fox\",move=\"nair\"} 123",
+            "shdl_move_count{character=\"Mr. \\\"G\\\\W\\\"\\nGame\",move=\"bair\"} 4",
+        ]);
+        assert!(output.contains("# TYPE shdl_move_count gauge\n"));
+    }
+
+    #[test]
+    fn test_markdown_tables() {
+        let mut stats = test_stats(vec![PlayerMoveData {
+            port: 0,
+            character: "Fox".to_string(),
+            moves: HashMap::from([("shine".to_string(), 12), ("nair".to_string(), 4)]),
+            ..Default::default()
+        }]);
+        stats.aggregated_stats.insert("most_common_move".to_string(), serde_json::Value::String("shine".to_string()));
+        
+        let markdown = generate_markdown_output(&stats);
+        assert!(markdown.contains("| Stat | Value |\n|---|---|\n"));
+        assert!(markdown.contains("| Most common move | shine |"));
+        assert!(markdown.contains("|---|---|---|---|\n| 0 | Fox | 16 | shine (12), nair (4) |"));
+    }
+
+    #[test]
+    fn test_ndjson_streams_one_line_per_game() {
+        let dir = tempfile::tempdir().unwrap();
+        for idx in 0..3 {
+            let game_data = GameData {
+                player_count: 1,
+                duration_frames: 1000,
+                stage: "Battlefield".to_string(),
+                moves: Some(vec![PlayerMoveData { port: 0, character: "Fox".to_string(), ..Default::default() }]),
+                ..Default::default()
+            };
+            fs::write(dir.path().join(format!("Game_{}.json", idx)), serde_json::to_string(&game_data).unwrap()).unwrap();
+        }
+        fs::write(dir.path().join("Game_bad.json"), "{\"player_count\": 2, \"dura").unwrap();
+        
+        let mut output = Vec::new();
+        let written = write_ndjson(&dir.path().to_path_buf(), false, false, &GameFilter::default(), &MoveAliases::default(), &mut output).unwrap();
+        assert_eq!(written, 3);
+        
+        let games: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(games.len(), 3);
+        assert!(games.iter().all(|game| game["stage"] == "Battlefield"));
+    }
+
+    #[test]
+    fn test_doubles_matchups_pair_opposing_teams() {
+        let player = |port: u8, code: &str, team: &str| PlayerData {
+            port,
+            character: "Fox".to_string(),
+            stocks: 4,
+            costume: 0,
+            team: Some(team.to_string()),
+            display_name: None,
+            connect_code: Some(code.to_string()),
+            controller: None,
+            characters: vec![],
+        };
+        let game_data = GameData {
+            player_count: 4,
+            duration_frames: 5000,
+            stage: "Battlefield".to_string(),
+            players: vec![player(0, "AAAA#1", "Red"), player(1, "BBBB#2", "Red"), player(2, "CCCC#3", "Blue"), player(3, "DDDD#4", "Blue")],
+            winning_team: Some("Blue".to_string()),
+            ..Default::default()
+        };
+        
+        let matchups = compute_matchups(&[game_data]);
+        assert_eq!(matchups.len(), 4);
+        assert!(matchups.iter().all(|m| m.player_a_wins == 0 && m.player_b_wins == 1));
+        assert!(!matchups.iter().any(|m| m.player_a == "AAAA#1" && m.player_b == "BBBB#2"));
+    }
+
+    #[test]
+    fn test_write_sqlite() {
+        let game_data = GameData {
+            player_count: 2,
+            duration_frames: 5000,
+            stage: "Battlefield".to_string(),
+            winner: Some(0),
+            moves: Some(vec![
+                PlayerMoveData {
+                    port: 0,
+                    character: "Fox".to_string(),
+                    moves: HashMap::from([("shine".to_string(), 12), ("nair".to_string(), 4)]),
+                    ..Default::default()
+                },
+                PlayerMoveData {
+                    port: 1,
+                    character: "Marth".to_string(),
+                    moves: HashMap::from([("fair".to_string(), 9)]),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        };
+        
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        write_sqlite(&mut conn, &[(PathBuf::from("Game_1.json"), game_data)]).unwrap();
+        
+        let shines: u32 = conn.query_row(
+            "SELECT SUM(moves.count) FROM moves JOIN players ON moves.player_id = players.id WHERE players.character = 'Fox' AND moves.move = 'shine'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(shines, 12);
+        
+        let winner: u8 = conn.query_row("SELECT winner FROM games", [], |row| row.get(0)).unwrap();
+        assert_eq!(winner, 0);
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::UInt32Type;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        
+        let game = |moves: Vec<PlayerMoveData>| GameData {
+            player_count: moves.len(),
+            duration_frames: 5000,
+            stage: "Battlefield".to_string(),
+            moves: Some(moves),
+            ..Default::default()
+        };
+        let games = vec![
+            (PathBuf::from("Game_1.json"), game(vec![
+                PlayerMoveData { port: 0, character: "Fox".to_string(), moves: HashMap::from([("shine".to_string(), 12), ("nair".to_string(), 4)]), ..Default::default() },
+                PlayerMoveData { port: 1, character: "Marth".to_string(), moves: HashMap::from([("fair".to_string(), 9)]), ..Default::default() },
+            ])),
+            (PathBuf::from("Game_2.json"), game(vec![
+                PlayerMoveData { port: 2, character: "Falco".to_string(), moves: HashMap::from([("laser".to_string(), 30)]), ..Default::default() },
+            ])),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.parquet");
+        assert_eq!(write_parquet(&games, File::create(&path).unwrap()).unwrap(), 4);
+        
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        let schema = reader.schema().clone();
+        assert_eq!(schema.field_with_name("count").unwrap().data_type(), &DataType::UInt32);
+        assert_eq!(schema.field_with_name("move").unwrap().data_type(), &DataType::Utf8);
+        let batches: Vec<RecordBatch> = reader.build().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 4);
+        
+        // Moves are written in name order, so Fox's shine is the second row
+        let batch = &batches[0];
+        assert_eq!(batch.column_by_name("game_file").unwrap().as_string::<i32>().value(1), "Game_1.json");
+        assert_eq!(batch.column_by_name("character").unwrap().as_string::<i32>().value(1), "Fox");
+        assert_eq!(batch.column_by_name("move").unwrap().as_string::<i32>().value(1), "shine");
+        assert_eq!(batch.column_by_name("count").unwrap().as_primitive::<UInt32Type>().value(1), 12);
+    }
+}
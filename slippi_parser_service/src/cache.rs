@@ -0,0 +1,99 @@
+// Content-addressed cache of parsed replays, so unchanged files aren't reparsed
+use anyhow::Result;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::GameData;
+
+// Bump whenever parsing or move extraction changes its output, so entries
+// written by an older build are never served
+const EXTRACTION_VERSION: &str = "35";
+
+pub struct GameCache {
+    dir: PathBuf,
+}
+
+impl GameCache {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(GameCache { dir })
+    }
+    
+    // $XDG_CACHE_HOME or ~/.cache, falling back to the system temp directory
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("slippi_parser_service")
+    }
+    
+    // Return the cached GameData for this file's contents, or run `parse` and
+    // cache its result. `variant` distinguishes outputs of the same replay
+    // under different options (e.g. with and without move extraction).
+    pub async fn get_or_parse(&self, file_path: &Path, variant: &str, parse: impl Future<Output = Result<GameData>>) -> Result<GameData> {
+        let hash = blake3::hash(&fs::read(file_path)?);
+        let cache_path = self.dir.join(format!("v{}-{}-{}.json", EXTRACTION_VERSION, variant, hash.to_hex()));
+        
+        if let Ok(cached) = fs::read_to_string(&cache_path) {
+            match serde_json::from_str(&cached) {
+                Ok(game_data) => {
+                    debug!("Cache hit for {:?}", file_path);
+                    return Ok(game_data);
+                }
+                Err(e) => warn!("Ignoring corrupt cache entry {:?}: {}", cache_path, e),
+            }
+        }
+        
+        let game_data = parse.await?;
+        // A failed write only costs a reparse next time
+        if let Err(e) = fs::write(&cache_path, serde_json::to_string(&game_data)?) {
+            warn!("Could not write cache entry {:?}: {}", cache_path, e);
+        }
+        
+        Ok(game_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    
+    fn test_game(stage: &str) -> GameData {
+        GameData { player_count: 2, duration_frames: 1000, stage: stage.to_string(), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_second_parse_hits_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let replay = dir.path().join("Game_1.slp");
+        fs::write(&replay, b"replay bytes").unwrap();
+        let cache = GameCache::new(dir.path().join("cache")).unwrap();
+        
+        let parses = Cell::new(0);
+        let parse = |stage: &'static str| {
+            let parses = &parses;
+            async move {
+                parses.set(parses.get() + 1);
+                Ok(test_game(stage))
+            }
+        };
+        
+        let first = cache.get_or_parse(&replay, "basic", parse("Battlefield")).await.unwrap();
+        let second = cache.get_or_parse(&replay, "basic", parse("Dream Land")).await.unwrap();
+        assert_eq!(parses.get(), 1);
+        assert_eq!(first.stage, "Battlefield");
+        assert_eq!(second.stage, "Battlefield");
+        
+        // Other options and changed contents are separate entries
+        cache.get_or_parse(&replay, "moves-merge", parse("Battlefield")).await.unwrap();
+        assert_eq!(parses.get(), 2);
+        fs::write(&replay, b"new replay bytes").unwrap();
+        let changed = cache.get_or_parse(&replay, "basic", parse("Dream Land")).await.unwrap();
+        assert_eq!(parses.get(), 3);
+        assert_eq!(changed.stage, "Dream Land");
+    }
+}
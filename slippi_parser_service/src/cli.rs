@@ -0,0 +1,110 @@
+//! Command-line plumbing shared by the parser service and `move_analyzer`:
+//! logging, the rayon pool, --fail-on-empty and the clap forms of the
+//! library's option enums. Each binary includes this file as its own module,
+//! so none of it is part of the library's API.
+
+// Each binary uses only some of what's here
+#![allow(dead_code)]
+
+use anyhow::Result;
+use tracing::error;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+use slippi_parser_service::{CsvLayout, GroupBy, IcsMode};
+
+/// How player entries are combined across games
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GroupByArg {
+    /// One entry per connect code / display name
+    Name,
+}
+
+impl From<GroupByArg> for GroupBy {
+    fn from(arg: GroupByArg) -> Self {
+        match arg {
+            GroupByArg::Name => GroupBy::Name,
+        }
+    }
+}
+
+/// How Ice Climbers' follower (Nana) is counted
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum IcsModeArg {
+    /// Add Nana's moves to the player's own counts
+    Merge,
+    /// Count Nana's moves separately as "<move>_nana"
+    Split,
+}
+
+impl From<IcsModeArg> for IcsMode {
+    fn from(arg: IcsModeArg) -> Self {
+        match arg {
+            IcsModeArg::Merge => IcsMode::Merge,
+            IcsModeArg::Split => IcsMode::Split,
+        }
+    }
+}
+
+/// Shape of the CSV output
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CsvLayoutArg {
+    /// port,character,move,count rows
+    Long,
+    /// One row per player, one column per move
+    Wide,
+}
+
+impl From<CsvLayoutArg> for CsvLayout {
+    fn from(arg: CsvLayoutArg) -> Self {
+        match arg {
+            CsvLayoutArg::Long => CsvLayout::Long,
+            CsvLayoutArg::Wide => CsvLayout::Wide,
+        }
+    }
+}
+
+// Log filter for -v/-q: errors only by default, info at -v, debug at -vv and
+// trace beyond, nothing with --quiet. Without either flag RUST_LOG is used,
+// with the usual directives (e.g. RUST_LOG=slippi_parser_service=debug,peppi=warn);
+// an unset or invalid RUST_LOG falls back to errors only.
+pub fn log_filter(verbose: u8, quiet: bool, rust_log: Option<&str>) -> EnvFilter {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::OFF,
+        (false, 0) => return rust_log
+            .and_then(|directives| EnvFilter::try_new(directives.trim()).ok())
+            .unwrap_or_else(|| EnvFilter::new(LevelFilter::ERROR.to_string())),
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    EnvFilter::new(level.to_string())
+}
+
+// Install the tracing subscriber. Logs go to stderr so they never mix with
+// JSON written to stdout.
+pub fn init_logging(verbose: u8, quiet: bool) {
+    let filter = log_filter(verbose, quiet, std::env::var("RUST_LOG").ok().as_deref());
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+// Size rayon's global pool for --jobs. Without it rayon uses one thread per
+// logical core; with 1, files are processed one at a time in path order.
+pub fn configure_jobs(jobs: Option<usize>) -> Result<()> {
+    if let Some(jobs) = jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global()?;
+    }
+    Ok(())
+}
+
+// For --fail-on-empty: an error when a directory run found no games to
+// process, so scripts notice a wrong path instead of getting empty results
+pub fn check_games_found(games: usize, fail_on_empty: bool) -> Result<()> {
+    if games == 0 && fail_on_empty {
+        error!("No games were processed");
+        return Err(anyhow::anyhow!("No games were processed"));
+    }
+    Ok(())
+}
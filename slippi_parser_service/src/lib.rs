@@ -0,0 +1,2292 @@
+//! Parsed game data and directory-level aggregation, shared by the parser
+//! service and `move_analyzer`.
+
+use anyhow::Result;
+use rayon::prelude::*;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{info, warn};
+
+pub mod action_states;
+pub mod analysis;
+pub mod stages;
+
+// Version of the GameData JSON format. Must be bumped whenever a field is
+// added to, renamed in or removed from GameData, PlayerData or PlayerMoveData,
+// just as cache::EXTRACTION_VERSION is for extraction changes: older files
+// would otherwise load through serde(default) with the new stats silently 0,
+// and --reparse-outdated would never pick them up.
+//
+// 2: every field added since 1 (transformations, invincible frames,
+//    actionable time, last-stock moves and the rest)
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GameData {
+    // Files written before the format was versioned read as 0
+    #[serde(default)]
+    pub schema_version: u32,
+    pub player_count: usize,
+    pub duration_frames: u32,
+    // Game time after GO!, matching the in-game clock, and as "m:ss"
+    #[serde(default)]
+    pub duration_seconds: f32,
+    #[serde(default)]
+    pub duration_display: String,
+    pub stage: String,
+    pub start_time: Option<String>,
+    // "ranked", "unranked", "direct" or "offline"; None when it can't be told
+    #[serde(default)]
+    pub match_type: Option<String>,
+    pub players: Vec<PlayerData>,
+    pub winner: Option<u8>,
+    // Team color of the winners in doubles; `winner` is only set for singles and free-for-alls
+    pub winning_team: Option<String>,
+    // How the winner (or winning team) won; None when there is no winner
+    #[serde(default)]
+    pub win_reason: Option<WinReason>,
+    // Ports that landed the game's first damaging hit and its last (usually
+    // the final blow); None when nobody was hit or moves weren't extracted
+    #[serde(default)]
+    pub first_hit_port: Option<u8>,
+    #[serde(default)]
+    pub last_hit_port: Option<u8>,
+    // Less than a second of play after GO! (e.g. an immediate quit); left out
+    // of aggregation unless asked for
+    #[serde(default)]
+    pub incomplete: bool,
+    pub moves: Option<Vec<PlayerMoveData>>,
+    // Every stock lost, in frame order (only filled in when moves are extracted)
+    #[serde(default)]
+    pub stock_events: Vec<StockEvent>,
+}
+
+// An empty game in the current schema, for building GameData with struct update syntax
+impl Default for GameData {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            player_count: 0,
+            duration_frames: 0,
+            duration_seconds: 0.0,
+            duration_display: String::new(),
+            stage: String::new(),
+            start_time: None,
+            match_type: None,
+            players: vec![],
+            winner: None,
+            winning_team: None,
+            win_reason: None,
+            first_hit_port: None,
+            last_hit_port: None,
+            incomplete: false,
+            moves: None,
+            stock_events: vec![],
+        }
+    }
+}
+
+impl GameData {
+    // Keep only the player on `port` (0-based, as in the output's `port`
+    // field) for --port. Returns false when nobody played on it.
+    pub fn retain_port(&mut self, port: u8) -> bool {
+        self.players.retain(|p| p.port == port);
+        if let Some(moves) = self.moves.as_mut() {
+            moves.retain(|p| p.port == port);
+        }
+        !self.players.is_empty() || self.moves.as_ref().is_some_and(|moves| !moves.is_empty())
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum WinReason {
+    // Every opponent ran out of stocks
+    #[serde(rename = "KO")]
+    Ko,
+    // The timer ran out; most stocks won, then lowest percent
+    Timeout,
+    // The opponent quit out with L+R+A+Start
+    #[serde(rename = "LRAS")]
+    Lras,
+}
+
+impl std::fmt::Display for WinReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            WinReason::Ko => "KO",
+            WinReason::Timeout => "Timeout",
+            WinReason::Lras => "LRAS",
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct StockEvent {
+    pub frame: u32,
+    pub port: u8,
+    pub remaining_stocks: u8,
+}
+
+// A counted move starting on a frame, for --timeline
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct MoveEvent {
+    pub frame: u32,
+    pub port: u8,
+    #[serde(rename = "move")]
+    pub move_name: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PlayerData {
+    pub port: u8,
+    pub character: String,
+    pub stocks: u8,
+    pub costume: u8,
+    pub team: Option<String>,
+    pub display_name: Option<String>,
+    pub connect_code: Option<String>,
+    // Controller fixes enabled for the player: "ucf", "arduino",
+    // "ucf+arduino" when dashback and shield drop differ, or "vanilla". Only
+    // recorded from Slippi 1.0 onwards.
+    #[serde(default)]
+    pub controller: Option<String>,
+    // Every character played, for Zelda/Sheik players who transformed during
+    // the game (`character` is the one they started as); empty otherwise
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub characters: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct PlayerMoveData {
+    pub port: u8,
+    pub character: String,
+    pub display_name: Option<String>,
+    pub connect_code: Option<String>,
+    pub team: Option<String>,
+    // Games this entry covers: 1 as extracted, summed by --group-by
+    #[serde(default)]
+    pub games_played: u32,
+    #[serde(serialize_with = "sorted_keys")]
+    pub moves: HashMap<String, u32>,
+    #[serde(default, serialize_with = "sorted_keys")]
+    pub move_percentages: HashMap<String, f32>,
+    #[serde(default, serialize_with = "sorted_keys")]
+    pub moves_per_minute: HashMap<String, f32>,
+    // Each move's count over stocks_used; only filled in with --per-stock
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted_keys")]
+    pub moves_per_stock: HashMap<String, f32>,
+    // The part of `moves` started on the player's last stock
+    #[serde(default, serialize_with = "sorted_keys")]
+    pub last_stock_moves: HashMap<String, u32>,
+    // For attacks: how many connected and how many whiffed (or were shielded)
+    #[serde(default, serialize_with = "sorted_keys")]
+    pub move_outcomes: HashMap<String, MoveOutcome>,
+    #[serde(default)]
+    pub damage_dealt: f32,
+    #[serde(default)]
+    pub damage_taken: f32,
+    #[serde(default)]
+    pub openings_won: u32,
+    #[serde(default)]
+    pub actions: u32,
+    #[serde(default)]
+    pub frames_played: u32,
+    #[serde(default)]
+    pub apm: f32,
+    #[serde(default)]
+    pub aerial_landings: u32,
+    #[serde(default)]
+    pub l_cancel_success_rate: f32,
+    #[serde(default)]
+    pub grab_success_rate: f32,
+    #[serde(default)]
+    pub shield_frames: u32,
+    // Frames spent invulnerable or intangible: respawn platform, ledge grabs,
+    // rolls and dodges. Needs Slippi 2.0 or later.
+    #[serde(default)]
+    pub invincible_frames: u32,
+    // Frames the player was free to act (standing, moving, jumping, falling,
+    // crouching, shielding or hanging on the ledge), and their share of
+    // frames_played; the rest is hitstun, lag and committed actions
+    #[serde(default)]
+    pub actionable_frames: u32,
+    #[serde(default)]
+    pub actionable_ratio: f32,
+    #[serde(default)]
+    pub combos: Vec<Combo>,
+    // Combos that ended with the victim back in neutral, rather than by a
+    // kill or another player's hit: dropped as the attacker, escaped as the victim
+    #[serde(default)]
+    pub combos_dropped: u32,
+    #[serde(default)]
+    pub combos_escaped: u32,
+    #[serde(default)]
+    pub edgeguards: Edgeguards,
+    #[serde(default)]
+    pub deaths: Vec<Death>,
+    #[serde(default)]
+    pub average_death_percent: f32,
+    // Stocks the player played: every one lost, plus the last if they survived
+    #[serde(default)]
+    pub stocks_used: u32,
+    // Stocks lost without an opponent's hit or grab leading to it
+    #[serde(default)]
+    pub self_destructs: u32,
+    // Hits received by how they were DI'd: "in", "out", "survival" or "none"
+    #[serde(default, serialize_with = "sorted_keys")]
+    pub di: HashMap<String, u32>,
+    #[serde(default)]
+    pub sdi_inputs: u32,
+    #[serde(default)]
+    pub total_galint: i32,
+    #[serde(default)]
+    pub galint_samples: u32,
+    #[serde(default)]
+    pub average_galint: f32,
+    // Zelda <-> Sheik transformations
+    #[serde(default)]
+    pub transformations: u32,
+    #[serde(default)]
+    pub ledge_grabs: u32,
+    // Frames spent on the ledge over the stays the player ended themselves
+    // (ledge_releases); stays ended by being hit off are ledge_knockoffs
+    #[serde(default)]
+    pub total_ledge_frames: u32,
+    #[serde(default)]
+    pub ledge_releases: u32,
+    #[serde(default)]
+    pub ledge_knockoffs: u32,
+    #[serde(default)]
+    pub average_ledge_time: f32,
+    #[serde(default)]
+    pub total_wavedash_angle: f32,
+    #[serde(default)]
+    pub total_wavedash_distance: f32,
+    #[serde(default)]
+    pub wavedash_samples: u32,
+    #[serde(default)]
+    pub average_wavedash_angle: f32,
+    #[serde(default)]
+    pub average_wavedash_distance: f32,
+    // Moves counted on the frame being analyzed, waiting to go on the timeline
+    #[serde(skip)]
+    pub started_moves: Vec<String>,
+}
+
+impl PlayerMoveData {
+    // Key used to recognise the same player across games: connect code, then
+    // display name, falling back to port and character for offline games
+    pub fn player_key(&self) -> String {
+        self.connect_code
+            .clone()
+            .or_else(|| self.display_name.clone())
+            .unwrap_or_else(|| format!("Port {} {}", self.port, self.character))
+    }
+    
+    // Each move's share of the player's total moves, as a percentage
+    pub fn compute_move_percentages(&self) -> HashMap<String, f32> {
+        let total: u32 = self.moves.values().sum();
+        if total == 0 {
+            return HashMap::new();
+        }
+        self.moves
+            .iter()
+            .map(|(move_name, &count)| (move_name.clone(), count as f32 * 100.0 / total as f32))
+            .collect()
+    }
+    
+    // Each move's count per minute of the player's game time. Grouped entries
+    // divide the summed counts by the summed game time rather than averaging
+    // each game's rate, so long games weigh more than short ones.
+    pub fn compute_moves_per_minute(&self) -> HashMap<String, f32> {
+        if self.frames_played == 0 {
+            return HashMap::new();
+        }
+        self.moves
+            .iter()
+            .map(|(move_name, &count)| (move_name.clone(), actions_per_minute(count, self.frames_played)))
+            .collect()
+    }
+    
+    // Each move's count per stock used. Like moves_per_minute, grouped entries
+    // divide summed counts by the summed stocks.
+    pub fn compute_moves_per_stock(&self) -> HashMap<String, f32> {
+        if self.stocks_used == 0 {
+            return HashMap::new();
+        }
+        self.moves
+            .iter()
+            .map(|(move_name, &count)| (move_name.clone(), count as f32 / self.stocks_used as f32))
+            .collect()
+    }
+    
+    // Share of aerial landings that were L-cancelled
+    pub fn compute_l_cancel_success_rate(&self) -> f32 {
+        if self.aerial_landings == 0 {
+            return 0.0;
+        }
+        self.moves.get("l_cancel").copied().unwrap_or(0) as f32 / self.aerial_landings as f32
+    }
+    
+    // Share of grabs that ended in a throw; the rest whiffed or were escaped
+    pub fn compute_grab_success_rate(&self) -> f32 {
+        let grabs = self.moves.get("grab").copied().unwrap_or(0);
+        if grabs == 0 {
+            return 0.0;
+        }
+        let throws: u32 = THROWS.iter().filter_map(|name| self.moves.get(*name)).sum();
+        throws as f32 / grabs as f32
+    }
+    
+    // Mean percent the player's stocks were lost at, optionally leaving out
+    // self-destructs, which would drag it towards 0
+    pub fn compute_average_death_percent(&self, exclude_self_destructs: bool) -> f32 {
+        let percents: Vec<f32> = self.deaths
+            .iter()
+            .filter(|death| !(exclude_self_destructs && death.self_destruct))
+            .map(|death| death.percent)
+            .collect();
+        if percents.is_empty() {
+            return 0.0;
+        }
+        percents.iter().sum::<f32>() / percents.len() as f32
+    }
+    
+    pub fn compute_actionable_ratio(&self) -> f32 {
+        if self.frames_played == 0 {
+            return 0.0;
+        }
+        self.actionable_frames as f32 / self.frames_played as f32
+    }
+    
+    pub fn compute_average_galint(&self) -> f32 {
+        if self.galint_samples == 0 {
+            return 0.0;
+        }
+        self.total_galint as f32 / self.galint_samples as f32
+    }
+    
+    pub fn compute_average_ledge_time(&self) -> f32 {
+        if self.ledge_releases == 0 {
+            return 0.0;
+        }
+        self.total_ledge_frames as f32 / self.ledge_releases as f32
+    }
+    
+    pub fn compute_wavedash_averages(&mut self) {
+        if self.wavedash_samples > 0 {
+            self.average_wavedash_angle = self.total_wavedash_angle / self.wavedash_samples as f32;
+            self.average_wavedash_distance = self.total_wavedash_distance / self.wavedash_samples as f32;
+        }
+    }
+    
+    // Fold another game's data for the same player into this one
+    pub fn merge(&mut self, other: PlayerMoveData) {
+        if !self.character.split(", ").any(|c| c == other.character) {
+            self.character = format!("{}, {}", self.character, other.character);
+        }
+        self.games_played += other.games_played;
+        for (move_name, count) in other.moves {
+            *self.moves.entry(move_name).or_insert(0) += count;
+        }
+        self.move_percentages = self.compute_move_percentages();
+        for (move_name, count) in other.last_stock_moves {
+            *self.last_stock_moves.entry(move_name).or_insert(0) += count;
+        }
+        for (move_name, outcome) in other.move_outcomes {
+            let totals = self.move_outcomes.entry(move_name).or_default();
+            totals.hits += outcome.hits;
+            totals.whiffs += outcome.whiffs;
+        }
+        // A grouped entry only keeps a team if every game was played on it
+        if self.team != other.team {
+            self.team = None;
+        }
+        self.damage_dealt += other.damage_dealt;
+        self.damage_taken += other.damage_taken;
+        self.openings_won += other.openings_won;
+        self.actions += other.actions;
+        self.frames_played += other.frames_played;
+        self.apm = actions_per_minute(self.actions, self.frames_played);
+        self.moves_per_minute = self.compute_moves_per_minute();
+        self.aerial_landings += other.aerial_landings;
+        self.l_cancel_success_rate = self.compute_l_cancel_success_rate();
+        self.grab_success_rate = self.compute_grab_success_rate();
+        self.shield_frames += other.shield_frames;
+        self.invincible_frames += other.invincible_frames;
+        self.actionable_frames += other.actionable_frames;
+        self.actionable_ratio = self.compute_actionable_ratio();
+        self.combos.extend(other.combos);
+        self.combos_dropped += other.combos_dropped;
+        self.combos_escaped += other.combos_escaped;
+        self.edgeguards.attempts += other.edgeguards.attempts;
+        self.edgeguards.successes += other.edgeguards.successes;
+        self.deaths.extend(other.deaths);
+        self.stocks_used += other.stocks_used;
+        self.average_death_percent = self.compute_average_death_percent(false);
+        self.self_destructs += other.self_destructs;
+        for (di, count) in other.di {
+            *self.di.entry(di).or_insert(0) += count;
+        }
+        self.sdi_inputs += other.sdi_inputs;
+        self.total_galint += other.total_galint;
+        self.galint_samples += other.galint_samples;
+        self.average_galint = self.compute_average_galint();
+        self.transformations += other.transformations;
+        self.ledge_grabs += other.ledge_grabs;
+        self.total_ledge_frames += other.total_ledge_frames;
+        self.ledge_releases += other.ledge_releases;
+        self.ledge_knockoffs += other.ledge_knockoffs;
+        self.average_ledge_time = self.compute_average_ledge_time();
+        self.total_wavedash_angle += other.total_wavedash_angle;
+        self.total_wavedash_distance += other.total_wavedash_distance;
+        self.wavedash_samples += other.wavedash_samples;
+        self.compute_wavedash_averages();
+    }
+}
+
+// A string of hits by one attacker on one victim before the victim got away
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct Combo {
+    pub victim: u8,
+    pub start_frame: u32,
+    pub starting_move: String,
+    pub hits: u32,
+    pub damage: f32,
+    pub killed: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug, PartialEq)]
+pub struct MoveOutcome {
+    pub hits: u32,
+    pub whiffs: u32,
+}
+
+// A lost stock: the percent on the frame before, and whether the player died
+// without having been hit recently
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+pub struct Death {
+    pub percent: f32,
+    pub self_destruct: bool,
+}
+
+// Attacks (or ledge grabs) against an opponent who was offstage, and how many
+// of those ended with the opponent losing a stock
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug, PartialEq)]
+pub struct Edgeguards {
+    pub attempts: u32,
+    pub successes: u32,
+}
+
+const THROWS: [&str; 4] = ["fthrow", "bthrow", "uthrow", "dthrow"];
+
+// Melee runs at 60 frames per second
+pub fn actions_per_minute(actions: u32, frames: u32) -> f32 {
+    if frames == 0 {
+        return 0.0;
+    }
+    actions as f32 * 3600.0 / frames as f32
+}
+
+// Replays start recording at the "Ready" countdown, 123 frames before GO!
+pub const COUNTDOWN_FRAMES: u32 = 123;
+
+// Games with less than this much play after GO! are marked incomplete
+pub const MIN_COMPLETE_SECONDS: f32 = 1.0;
+
+// Game time in seconds for a replay of `duration_frames` frames
+pub fn duration_seconds(duration_frames: u32) -> f32 {
+    duration_frames.saturating_sub(COUNTDOWN_FRAMES) as f32 / 60.0
+}
+
+// "m:ss", counting whole seconds like the in-game clock
+pub fn duration_display(seconds: f32) -> String {
+    let whole_seconds = seconds as u32;
+    format!("{}:{:02}", whole_seconds / 60, whole_seconds % 60)
+}
+
+// Which games to include, and when grouping by name, which players
+#[derive(Clone, Debug, Default)]
+pub struct GameFilter {
+    pub character: Option<String>,
+    // Keep only this port's players (as numbered in the output), dropping
+    // games it didn't play in
+    pub port: Option<u8>,
+    pub stages: Vec<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    // Games without a recorded match type are left out when set
+    pub match_type: Option<String>,
+    // Keep games marked incomplete, which are skipped by default
+    pub include_incomplete: bool,
+    // Skip files whose contents match one already included
+    pub dedup: bool,
+}
+
+impl GameFilter {
+    // Apply the filter to a loaded game, returning None if it should be skipped
+    fn apply(&self, path: &Path, mut game_data: GameData, group_by: Option<GroupBy>) -> Option<GameData> {
+        if game_data.incomplete && !self.include_incomplete {
+            return None;
+        }
+        
+        // With a date range, games whose date can't be determined are left out
+        if self.since.is_some() || self.until.is_some() {
+            let date = parse_game_date(path, &game_data)?.date();
+            if self.since.is_some_and(|since| date < since) || self.until.is_some_and(|until| date > until) {
+                return None;
+            }
+        }
+        
+        if let Some(match_type) = &self.match_type {
+            if game_data.match_type.as_deref().map(normalize_name) != Some(normalize_name(match_type)) {
+                return None;
+            }
+        }
+        
+        if !self.stages.is_empty() {
+            let stage = normalize_name(&game_data.stage);
+            if !self.stages.iter().any(|s| normalize_name(s) == stage) {
+                return None;
+            }
+        }
+        
+        // Narrowed to the port first, so --character then has to match that player
+        if let Some(port) = self.port {
+            if !game_data.retain_port(port) {
+                return None;
+            }
+        }
+        
+        if let Some(character) = &self.character {
+            let wanted = normalize_name(character);
+            let plays = |name: &str| normalize_name(name) == wanted;
+            
+            let in_game = game_data.players.iter().any(|p| plays(&p.character))
+                || game_data.moves.iter().flatten().any(|p| plays(&p.character));
+            if !in_game {
+                return None;
+            }
+            
+            // Grouped totals should only include the ports playing that character
+            if group_by.is_some() {
+                if let Some(moves) = game_data.moves.as_mut() {
+                    moves.retain(|p| plays(&p.character));
+                }
+            }
+        }
+        
+        Some(game_data)
+    }
+}
+
+// When a game was played: the recorded start time, else the file name
+fn parse_game_date(path: &Path, game: &GameData) -> Option<NaiveDateTime> {
+    game.start_time
+        .as_deref()
+        .and_then(|start_time| DateTime::parse_from_rfc3339(start_time).ok())
+        .map(|start_time| start_time.naive_utc())
+        .or_else(|| date_from_file_name(path))
+}
+
+// Parse the Game_YYYYMMDDTHHMMSS file name Slippi gives replays
+pub fn date_from_file_name(path: &Path) -> Option<NaiveDateTime> {
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDateTime::parse_from_str(stem.strip_prefix("Game_")?, "%Y%m%dT%H%M%S").ok()
+}
+
+// Lowercase and strip spaces and punctuation, so "Captain Falcon" matches
+// "captainfalcon" and "Pokémon Stadium" matches "pokemonstadium"
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .map(|c| if c == 'é' { 'e' } else { c })
+        .collect()
+}
+
+// How player entries are combined across games
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    // One entry per connect code / display name
+    Name,
+}
+
+// How Ice Climbers' follower (Nana) is counted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IcsMode {
+    // Add Nana's moves to the player's own counts
+    #[default]
+    Merge,
+    // Count Nana's moves separately as "<move>_nana"
+    Split,
+}
+
+// Shape of the CSV output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsvLayout {
+    // port,character,move,count rows
+    Long,
+    // One row per player, one column per move
+    Wide,
+}
+
+// Frames a player must spend out of hitstun before the next hit on them counts
+// as a new opening rather than a continuation of the combo
+pub const OPENING_RESET_FRAMES: u32 = 45;
+
+// The frames move extraction looks at, as indexes into the replay (0 is the
+// first recorded frame). Negative values count back from the end, and the end
+// is exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameRange {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl FrameRange {
+    // Resolve against a game's frame count, clamping to the frames that exist
+    pub fn clamp(&self, frame_count: usize) -> Range<usize> {
+        let resolve = |frame: i64| {
+            let frame_idx = usize::try_from(frame.unsigned_abs()).unwrap_or(usize::MAX);
+            if frame < 0 { frame_count.saturating_sub(frame_idx) } else { frame_idx.min(frame_count) }
+        };
+        let start = self.start.map_or(0, resolve);
+        let end = self.end.map_or(frame_count, resolve);
+        start..end.max(start)
+    }
+}
+
+// Everything that controls how replays are parsed and directories aggregated.
+// The CLI builds one from its arguments; library users can start from
+// AnalysisConfig::builder() and set only what they need.
+#[derive(Clone, Debug)]
+pub struct AnalysisConfig {
+    pub extract_moves: bool,
+    // Read only a replay's start, end and metadata, skipping its frames (and so
+    // move extraction) for a fast catalogue of games
+    pub no_moves: bool,
+    pub ics_mode: IcsMode,
+    pub frame_range: FrameRange,
+    pub combo_reset_frames: u32,
+    pub recursive: bool,
+    // Fail on the first file that doesn't parse instead of skipping it
+    pub strict: bool,
+    pub group_by: Option<GroupBy>,
+    // Leave out players seen in fewer games than this, from the aggregates too
+    pub min_games: u32,
+    pub filter: GameFilter,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        AnalysisConfig {
+            extract_moves: false,
+            no_moves: false,
+            ics_mode: IcsMode::default(),
+            frame_range: FrameRange::default(),
+            combo_reset_frames: OPENING_RESET_FRAMES,
+            recursive: false,
+            strict: false,
+            group_by: None,
+            min_games: 0,
+            filter: GameFilter::default(),
+        }
+    }
+}
+
+impl AnalysisConfig {
+    pub fn builder() -> AnalysisConfigBuilder {
+        AnalysisConfigBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct AnalysisConfigBuilder {
+    config: AnalysisConfig,
+}
+
+impl AnalysisConfigBuilder {
+    pub fn extract_moves(mut self, extract_moves: bool) -> Self {
+        self.config.extract_moves = extract_moves;
+        self
+    }
+    
+    pub fn no_moves(mut self, no_moves: bool) -> Self {
+        self.config.no_moves = no_moves;
+        self
+    }
+    
+    pub fn ics_mode(mut self, ics_mode: IcsMode) -> Self {
+        self.config.ics_mode = ics_mode;
+        self
+    }
+    
+    pub fn frame_range(mut self, frame_range: FrameRange) -> Self {
+        self.config.frame_range = frame_range;
+        self
+    }
+    
+    pub fn combo_reset_frames(mut self, combo_reset_frames: u32) -> Self {
+        self.config.combo_reset_frames = combo_reset_frames;
+        self
+    }
+    
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.config.recursive = recursive;
+        self
+    }
+    
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+    
+    pub fn group_by(mut self, group_by: Option<GroupBy>) -> Self {
+        self.config.group_by = group_by;
+        self
+    }
+    
+    pub fn min_games(mut self, min_games: u32) -> Self {
+        self.config.min_games = min_games;
+        self
+    }
+    
+    pub fn filter(mut self, filter: GameFilter) -> Self {
+        self.config.filter = filter;
+        self
+    }
+    
+    pub fn build(self) -> AnalysisConfig {
+        self.config
+    }
+}
+
+// Merge per-game player entries into one entry per player, keeping first-seen order
+pub fn group_players_by_name(players: Vec<PlayerMoveData>) -> Vec<PlayerMoveData> {
+    let mut grouped: Vec<PlayerMoveData> = Vec::new();
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    
+    for player in players {
+        let key = player.player_key();
+        match index_by_key.get(&key) {
+            Some(&idx) => grouped[idx].merge(player),
+            None => {
+                index_by_key.insert(key, grouped.len());
+                grouped.push(player);
+            }
+        }
+    }
+    
+    grouped
+}
+
+// Move and damage totals for one team color across the scanned games
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TeamMoveData {
+    pub team: String,
+    pub players: Vec<String>,
+    #[serde(serialize_with = "sorted_keys")]
+    pub moves: HashMap<String, u32>,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+}
+
+// Character name to move name to figure
+pub type CharacterProfiles<T> = HashMap<String, HashMap<String, T>>;
+
+// Write a map's entries in key order, so the same stats always serialize to
+// the same bytes whatever order the hash map holds them in
+fn sorted_keys<T: serde::Serialize, S: serde::Serializer>(map: &HashMap<String, T>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+// The same for character profiles, sorting both characters and moves
+fn sorted_profiles<T: serde::Serialize, S: serde::Serializer>(profiles: &CharacterProfiles<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(profiles.iter().map(|(character, moves)| (character, moves.iter().collect::<BTreeMap<_, _>>())).collect::<BTreeMap<_, _>>())
+}
+
+// Sum move counts per character. Each player entry covers games_played games
+// as that character (one, unless read back from grouped stats), which the
+// per-game figures divide by, rounded to two decimal places.
+fn character_profiles(players: &[PlayerMoveData]) -> (CharacterProfiles<u32>, CharacterProfiles<f64>) {
+    let mut profiles: CharacterProfiles<u32> = HashMap::new();
+    let mut games: HashMap<&str, u32> = HashMap::new();
+    for player in players {
+        *games.entry(&player.character).or_insert(0) += player.games_played.max(1);
+        let profile = profiles.entry(player.character.clone()).or_default();
+        for (move_name, count) in &player.moves {
+            *profile.entry(move_name.clone()).or_insert(0) += count;
+        }
+    }
+    
+    let per_game = profiles
+        .iter()
+        .map(|(character, moves)| {
+            let games = games[character.as_str()] as f64;
+            let averages = moves
+                .iter()
+                .map(|(move_name, &count)| (move_name.clone(), (count as f64 / games * 100.0).round() / 100.0))
+                .collect();
+            (character.clone(), averages)
+        })
+        .collect();
+    
+    (profiles, per_game)
+}
+
+// Sum every player entry that has a team into per-team totals, keeping first-seen order
+fn team_totals(players: &[PlayerMoveData]) -> Vec<TeamMoveData> {
+    let mut teams: Vec<TeamMoveData> = Vec::new();
+    
+    for player in players {
+        let Some(team) = &player.team else {
+            continue;
+        };
+        let idx = match teams.iter().position(|t| &t.team == team) {
+            Some(idx) => idx,
+            None => {
+                teams.push(TeamMoveData {
+                    team: team.clone(),
+                    players: Vec::new(),
+                    moves: HashMap::new(),
+                    damage_dealt: 0.0,
+                    damage_taken: 0.0,
+                });
+                teams.len() - 1
+            }
+        };
+        
+        let totals = &mut teams[idx];
+        let key = player.player_key();
+        if !totals.players.contains(&key) {
+            totals.players.push(key);
+        }
+        for (move_name, count) in &player.moves {
+            *totals.moves.entry(move_name.clone()).or_insert(0) += count;
+        }
+        totals.damage_dealt += player.damage_dealt;
+        totals.damage_taken += player.damage_taken;
+    }
+    
+    teams
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MoveStats {
+    pub total_games: u32,
+    // None, and left out of the output entirely, after --summary-only; an
+    // empty list is still written when no players were found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub players: Option<Vec<PlayerMoveData>>,
+    // Only present when some games were played in teams
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teams: Vec<TeamMoveData>,
+    #[serde(serialize_with = "sorted_keys")]
+    pub aggregated_stats: HashMap<String, serde_json::Value>,
+    // Every move with its total count, most used first
+    #[serde(default)]
+    pub ranked_moves: Vec<(String, u32)>,
+    // Move totals for each character across all players and games
+    #[serde(default, serialize_with = "sorted_profiles")]
+    pub character_profiles: CharacterProfiles<u32>,
+    // The same totals divided by how many times the character was played
+    #[serde(default, serialize_with = "sorted_profiles")]
+    pub character_profiles_per_game: CharacterProfiles<f64>,
+    pub failed_files: Vec<FailedFile>,
+    // Files left out by --dedup
+    #[serde(default)]
+    pub duplicates_skipped: usize,
+}
+
+impl MoveStats {
+    // Drop the per-player entries, which dominate the output for large
+    // directories, keeping only the totals (--summary-only)
+    pub fn summarize(&mut self) {
+        self.players = None;
+    }
+    
+    // The per-player entries, or none after summarize()
+    pub fn players(&self) -> &[PlayerMoveData] {
+        self.players.as_deref().unwrap_or_default()
+    }
+}
+
+// User-preferred move names (--alias-file), a JSON object from the name the
+// parser uses to the one to print, e.g. {"nair": "neutral_air"}. Aliases are
+// only applied to output; detection and cached data keep the built-in names.
+// Moves without an alias keep their own name.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(transparent)]
+pub struct MoveAliases(HashMap<String, String>);
+
+impl MoveAliases {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("{:?} is not a move alias file: {}", path, e))
+    }
+    
+    pub fn name<'a>(&'a self, move_name: &'a str) -> &'a str {
+        self.0.get(move_name).map_or(move_name, String::as_str)
+    }
+    
+    fn rename_keys<T>(&self, map: &mut HashMap<String, T>) {
+        if !self.0.is_empty() {
+            *map = map.drain().map(|(move_name, value)| (self.name(&move_name).to_string(), value)).collect();
+        }
+    }
+    
+    pub fn rename_player(&self, player: &mut PlayerMoveData) {
+        self.rename_keys(&mut player.moves);
+        self.rename_keys(&mut player.move_percentages);
+        self.rename_keys(&mut player.moves_per_minute);
+        self.rename_keys(&mut player.moves_per_stock);
+        self.rename_keys(&mut player.last_stock_moves);
+        self.rename_keys(&mut player.move_outcomes);
+        for combo in &mut player.combos {
+            combo.starting_move = self.name(&combo.starting_move).to_string();
+        }
+    }
+    
+    pub fn rename_stats(&self, stats: &mut MoveStats) {
+        for player in stats.players.iter_mut().flatten() {
+            self.rename_player(player);
+        }
+        for team in &mut stats.teams {
+            self.rename_keys(&mut team.moves);
+        }
+        for (move_name, _) in &mut stats.ranked_moves {
+            *move_name = self.name(move_name).to_string();
+        }
+        for profile in stats.character_profiles.values_mut() {
+            self.rename_keys(profile);
+        }
+        for profile in stats.character_profiles_per_game.values_mut() {
+            self.rename_keys(profile);
+        }
+        if let Some(serde_json::Value::String(most_common)) = stats.aggregated_stats.get_mut("most_common_move") {
+            *most_common = self.name(most_common).to_string();
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct FailedFile {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+// One game's results, for --per-game output
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GameResult {
+    pub file: PathBuf,
+    pub stage: String,
+    pub start_time: Option<String>,
+    #[serde(default)]
+    pub match_type: Option<String>,
+    pub players: Vec<PlayerData>,
+    pub winner: Option<u8>,
+    pub winning_team: Option<String>,
+    #[serde(default)]
+    pub win_reason: Option<WinReason>,
+    #[serde(default)]
+    pub first_hit_port: Option<u8>,
+    #[serde(default)]
+    pub last_hit_port: Option<u8>,
+    #[serde(default)]
+    pub incomplete: bool,
+    pub moves: Vec<PlayerMoveData>,
+    pub stock_events: Vec<StockEvent>,
+}
+
+impl GameResult {
+    pub fn new(file: PathBuf, game_data: GameData) -> Self {
+        GameResult {
+            file,
+            stage: game_data.stage,
+            start_time: game_data.start_time,
+            match_type: game_data.match_type,
+            players: game_data.players,
+            winner: game_data.winner,
+            winning_team: game_data.winning_team,
+            win_reason: game_data.win_reason,
+            first_hit_port: game_data.first_hit_port,
+            last_hit_port: game_data.last_hit_port,
+            incomplete: game_data.incomplete,
+            moves: game_data.moves.unwrap_or_default(),
+            stock_events: game_data.stock_events,
+        }
+    }
+}
+
+// Running totals for a directory scan. Each rayon worker builds its own
+// partial totals, which are merged in order so the result matches a serial scan.
+#[derive(Default)]
+struct DirectoryTotals {
+    total_games: u32,
+    players: Vec<PlayerMoveData>,
+    aggregated_moves: HashMap<String, u32>,
+    errors: Vec<(PathBuf, String)>,
+}
+
+impl DirectoryTotals {
+    fn add_file(self, (path, result): (&PathBuf, Result<GameData>)) -> Self {
+        match result {
+            Ok(game_data) => self.add_game(game_data),
+            Err(e) => self.add_error(path, e),
+        }
+    }
+    
+    fn add_error(mut self, path: &PathBuf, error: anyhow::Error) -> Self {
+        warn!("Skipping {:?}: {}", path, error);
+        self.errors.push((path.clone(), error.to_string()));
+        self
+    }
+    
+    fn add_game(mut self, game_data: GameData) -> Self {
+        self.total_games += 1;
+        
+        if let Some(moves) = game_data.moves {
+            for mut player_moves in moves {
+                // Files written before names were recorded on move data only carry them on players
+                if let Some(player) = game_data.players.iter().find(|p| p.port == player_moves.port) {
+                    player_moves.display_name = player_moves.display_name.or_else(|| player.display_name.clone());
+                    player_moves.connect_code = player_moves.connect_code.or_else(|| player.connect_code.clone());
+                    player_moves.team = player_moves.team.or_else(|| player.team.clone());
+                }
+                self.add_player(player_moves);
+            }
+        }
+        
+        self
+    }
+    
+    fn add_player(&mut self, mut player_moves: PlayerMoveData) {
+        // Entries from a single game, or saved before games were counted, cover at least one
+        player_moves.games_played = player_moves.games_played.max(1);
+        
+        // Files written before percentages were recorded don't have them
+        player_moves.move_percentages = player_moves.compute_move_percentages();
+        player_moves.moves_per_minute = player_moves.compute_moves_per_minute();
+        
+        // Aggregate moves
+        for (move_name, count) in &player_moves.moves {
+            let total_count = self.aggregated_moves.entry(move_name.clone()).or_insert(0);
+            *total_count += count;
+        }
+        
+        // Store player data
+        self.players.push(player_moves);
+    }
+    
+    fn merge(mut self, other: Self) -> Self {
+        self.total_games += other.total_games;
+        self.players.extend(other.players);
+        self.errors.extend(other.errors);
+        for (move_name, count) in other.aggregated_moves {
+            *self.aggregated_moves.entry(move_name).or_insert(0) += count;
+        }
+        
+        self
+    }
+    
+    fn into_move_stats(mut self, group_by: Option<GroupBy>, min_games: u32) -> MoveStats {
+        // Files are loaded in path order; sort players by who they are so runs
+        // over the same directory always list them the same way
+        self.players.sort_by(|a, b| {
+            let key = |player: &PlayerMoveData| (player.connect_code.clone().or_else(|| player.display_name.clone()), player.port);
+            key(a).cmp(&key(b))
+        });
+        
+        // Players seen in fewer than min_games games are left out of the
+        // aggregates as well as the player list. Games are counted per player
+        // whether or not entries are grouped.
+        if min_games > 1 {
+            let mut games: HashMap<String, u32> = HashMap::new();
+            for player in &self.players {
+                *games.entry(player.player_key()).or_insert(0) += player.games_played;
+            }
+            self.players.retain(|player| games[&player.player_key()] >= min_games);
+            self.aggregated_moves.clear();
+            for (move_name, count) in self.players.iter().flat_map(|player| &player.moves) {
+                *self.aggregated_moves.entry(move_name.clone()).or_insert(0) += count;
+            }
+        }
+        
+        // Create aggregated statistics
+        let mut stats_map = HashMap::new();
+        let ranked_moves = rank_moves(&self.aggregated_moves);
+        if let Some((most_common, _)) = ranked_moves.first() {
+            stats_map.insert("most_common_move".to_string(), serde_json::Value::String(most_common.clone()));
+        }
+        
+        let total_moves: u32 = self.aggregated_moves.values().sum();
+        let avg_moves_per_game = if self.total_games == 0 { 0.0 } else { total_moves as f64 / self.total_games as f64 };
+        // Rounded to one decimal place
+        stats_map.insert("average_moves_per_game".to_string(), serde_json::Value::from((avg_moves_per_game * 10.0).round() / 10.0));
+        
+        let failed_files = self.errors
+            .into_iter()
+            .map(|(path, error)| FailedFile { path, error })
+            .collect();
+        
+        let teams = team_totals(&self.players);
+        let (character_profiles, character_profiles_per_game) = character_profiles(&self.players);
+        let players = match group_by {
+            Some(GroupBy::Name) => group_players_by_name(self.players),
+            None => self.players,
+        };
+        
+        MoveStats {
+            total_games: self.total_games,
+            players: Some(players),
+            teams,
+            aggregated_stats: stats_map,
+            ranked_moves,
+            character_profiles,
+            character_profiles_per_game,
+            failed_files,
+            duplicates_skipped: 0,
+        }
+    }
+}
+
+// A file of saved results: aggregated statistics, or --per-game output
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StatsFile {
+    Totals(Box<MoveStats>),
+    Games(Vec<GameResult>),
+}
+
+// Combine previously saved statistics and per-game files into one set of
+// totals, without going back to the games themselves
+pub fn merge_stats_files(paths: &[PathBuf], group_by: Option<GroupBy>) -> Result<MoveStats> {
+    let mut totals = DirectoryTotals::default();
+    let mut duplicates_skipped = 0;
+    for path in paths {
+        let content = fs::read_to_string(path)?;
+        let stats_file = serde_json::from_str::<StatsFile>(&content)
+            .map_err(|e| anyhow::anyhow!("{:?} is neither move statistics nor per-game results: {}", path, e))?;
+        match stats_file {
+            StatsFile::Totals(stats) => {
+                // --summary-only output keeps its game count but not the moves
+                // behind it, which would skew every merged average
+                let players = match stats.players {
+                    Some(players) => players,
+                    None if stats.total_games == 0 => vec![],
+                    None => return Err(anyhow::anyhow!("{:?} was written with --summary-only, so it has no player entries to merge", path)),
+                };
+                totals.total_games += stats.total_games;
+                duplicates_skipped += stats.duplicates_skipped;
+                totals.errors.extend(stats.failed_files.into_iter().map(|failed| (failed.path, failed.error)));
+                for player in players {
+                    totals.add_player(player);
+                }
+            }
+            StatsFile::Games(games) => {
+                for game in games {
+                    totals.total_games += 1;
+                    for player in game.moves {
+                        totals.add_player(player);
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok(MoveStats { duplicates_skipped, ..totals.into_move_stats(group_by, 0) })
+}
+
+// Per-player metrics compared by `diff`, in display order. Rates are fractions,
+// so their deltas are in percentage points once multiplied by 100.
+pub const DIFF_METRICS: [&str; 10] = [
+    "apm",
+    "l_cancel_success_rate",
+    "grab_success_rate",
+    "damage_dealt",
+    "damage_taken",
+    "openings_won",
+    "shield_frames",
+    "average_galint",
+    "average_wavedash_angle",
+    "average_wavedash_distance",
+];
+
+fn diff_metric(player: &PlayerMoveData, metric: &str) -> f64 {
+    match metric {
+        "apm" => player.apm as f64,
+        "l_cancel_success_rate" => player.l_cancel_success_rate as f64,
+        "grab_success_rate" => player.grab_success_rate as f64,
+        "damage_dealt" => player.damage_dealt as f64,
+        "damage_taken" => player.damage_taken as f64,
+        "openings_won" => player.openings_won as f64,
+        "shield_frames" => player.shield_frames as f64,
+        "average_galint" => player.average_galint as f64,
+        "average_wavedash_angle" => player.average_wavedash_angle as f64,
+        "average_wavedash_distance" => player.average_wavedash_distance as f64,
+        _ => unreachable!("unknown diff metric {}", metric),
+    }
+}
+
+// How one player's statistics changed between two outputs. Only moves and
+// metrics that changed are listed.
+#[derive(serde::Serialize)]
+pub struct PlayerDiff {
+    pub player: String,
+    pub moves: BTreeMap<String, i64>,
+    pub metrics: BTreeMap<String, f64>,
+}
+
+// Changes from an earlier MoveStats output to a later one
+#[derive(serde::Serialize)]
+pub struct StatsDiff {
+    pub total_games: i64,
+    pub players: Vec<PlayerDiff>,
+    // Players that appear in only one of the outputs
+    pub only_before: Vec<String>,
+    pub only_after: Vec<String>,
+}
+
+// Compare two statistics outputs player by player, matching players by
+// connect code or display name (see `player_key`)
+pub fn diff_stats(before: MoveStats, after: MoveStats) -> StatsDiff {
+    let mut before_players: HashMap<String, PlayerMoveData> = group_players_by_name(before.players.unwrap_or_default())
+        .into_iter()
+        .map(|player| (player.player_key(), player))
+        .collect();
+    
+    let mut players = Vec::new();
+    let mut only_after = Vec::new();
+    for player in group_players_by_name(after.players.unwrap_or_default()) {
+        let key = player.player_key();
+        let Some(earlier) = before_players.remove(&key) else {
+            only_after.push(key);
+            continue;
+        };
+        
+        let move_names: HashSet<&String> = earlier.moves.keys().chain(player.moves.keys()).collect();
+        let moves = move_names
+            .into_iter()
+            .map(|name| {
+                let count = |p: &PlayerMoveData| p.moves.get(name).copied().unwrap_or(0) as i64;
+                (name.clone(), count(&player) - count(&earlier))
+            })
+            .filter(|(_, delta)| *delta != 0)
+            .collect();
+        // Rounded to four decimal places to hide f32 noise
+        let metrics = DIFF_METRICS
+            .iter()
+            .map(|metric| (metric.to_string(), ((diff_metric(&player, metric) - diff_metric(&earlier, metric)) * 10000.0).round() / 10000.0))
+            .filter(|(_, delta)| *delta != 0.0)
+            .collect();
+        players.push(PlayerDiff { player: key, moves, metrics });
+    }
+    
+    let mut only_before: Vec<String> = before_players.into_keys().collect();
+    only_before.sort();
+    
+    StatsDiff {
+        total_games: after.total_games as i64 - before.total_games as i64,
+        players,
+        only_before,
+        only_after,
+    }
+}
+
+// Moves sorted by count, highest first, with ties in name order so the
+// ranking doesn't depend on hash map iteration order
+fn rank_moves(moves: &HashMap<String, u32>) -> Vec<(String, u32)> {
+    let mut ranked: Vec<(String, u32)> = moves.iter().map(|(name, &count)| (name.clone(), count)).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+// List the JSON files in a directory, descending into subdirectories if requested
+fn collect_json_files(directory: &PathBuf, recursive: bool) -> Result<Vec<PathBuf>> {
+    collect_files(directory, recursive, "json")
+}
+
+// The JSON files to load for a directory scan, minus duplicates when the
+// filter asks for dedup. Also returns how many duplicates were left out.
+pub fn collect_game_files(directory: &PathBuf, recursive: bool, filter: &GameFilter) -> Result<(Vec<PathBuf>, usize)> {
+    let mut paths = collect_json_files(directory, recursive)?;
+    let duplicates = if filter.dedup { skip_duplicates(&mut paths) } else { 0 };
+    if duplicates > 0 {
+        info!("Skipped {} duplicate files", duplicates);
+    }
+    Ok((paths, duplicates))
+}
+
+// Drop files whose bytes match an earlier file, keeping the first copy in path
+// order. Unreadable files are kept so they're reported as failures later.
+fn skip_duplicates(paths: &mut Vec<PathBuf>) -> usize {
+    paths.sort();
+    let hashes: Vec<Option<blake3::Hash>> = paths
+        .par_iter()
+        .map(|path| fs::read(path).ok().map(|bytes| blake3::hash(&bytes)))
+        .collect();
+    
+    let mut seen = HashSet::new();
+    let mut hashes = hashes.into_iter();
+    let before = paths.len();
+    paths.retain(|_| hashes.next().flatten().is_none_or(|hash| seen.insert(hash)));
+    before - paths.len()
+}
+
+// List the files in a directory whose names end in `.<extension>`; the
+// extension may have several parts, like "slp.gz". Symlinked directories aren't
+// followed, so a link back up the tree can't recurse forever.
+pub fn collect_files(directory: &PathBuf, recursive: bool, extension: &str) -> Result<Vec<PathBuf>> {
+    let suffix = format!(".{}", extension);
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                paths.extend(collect_files(&path, recursive, extension)?);
+            }
+        } else if path.file_name().is_some_and(|name| name.to_string_lossy().ends_with(&suffix)) {
+            paths.push(path);
+        }
+    }
+    // read_dir's order is up to the filesystem
+    paths.sort();
+    
+    Ok(paths)
+}
+
+// Load a parsed game from a GameData JSON file. Files in another schema version
+// are rejected rather than risk aggregating misread data.
+fn load_game_data(path: &PathBuf) -> Result<GameData> {
+    let content = fs::read_to_string(path)?;
+    let game_data = serde_json::from_str::<GameData>(&content)?;
+    if game_data.schema_version != SCHEMA_VERSION {
+        return Err(anyhow::anyhow!("schema version {} (expected {}); regenerate it with --reparse-outdated", game_data.schema_version, SCHEMA_VERSION));
+    }
+    Ok(game_data)
+}
+
+// Just the version of a GameData file, without the rest of it
+#[derive(serde::Deserialize)]
+struct SchemaVersion {
+    #[serde(default)]
+    schema_version: u32,
+}
+
+// The JSON files in a directory written in another schema version. Files that
+// can't be read at all aren't listed; the scan reports them as failures.
+pub fn outdated_game_files(directory: &PathBuf, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut paths = collect_json_files(directory, recursive)?;
+    paths.retain(|path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SchemaVersion>(&content).ok())
+            .is_some_and(|version| version.schema_version != SCHEMA_VERSION)
+    });
+    paths.sort();
+    Ok(paths)
+}
+
+// Load a game and apply the filter; None means the game was filtered out
+pub fn load_filtered_game(path: &PathBuf, filter: &GameFilter, group_by: Option<GroupBy>) -> Option<Result<GameData>> {
+    match load_game_data(path) {
+        Ok(game_data) => filter.apply(path, game_data, group_by).map(Ok),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+// Load every game in a directory, skipping unparseable files unless strict
+pub fn load_games(directory: &PathBuf, recursive: bool, strict: bool, filter: &GameFilter) -> Result<Vec<(PathBuf, GameData)>> {
+    let (paths, _) = collect_game_files(directory, recursive, filter)?;
+    let results: Vec<(PathBuf, Result<GameData>)> = paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let result = load_filtered_game(&path, filter, None)?;
+            Some((path, result))
+        })
+        .collect();
+    
+    let mut games = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(game_data) => games.push((path, game_data)),
+            Err(e) if strict => return Err(anyhow::anyhow!("Failed to parse {:?}: {}", path, e)),
+            Err(e) => warn!("Skipping {:?}: {}", path, e),
+        }
+    }
+    
+    Ok(games)
+}
+
+// Process directory of JSON files into one result per game
+pub fn process_directory_per_game(directory: &PathBuf, config: &AnalysisConfig) -> Result<Vec<GameResult>> {
+    let games = load_games(directory, config.recursive, config.strict, &config.filter)?;
+    Ok(games.into_iter().map(|(path, game_data)| GameResult::new(path, game_data)).collect())
+}
+
+// Write `value` as JSON into `writer`, followed by a newline; indented when
+// `pretty`, otherwise on a single line (--compact). The document is serialized
+// straight into a buffered writer rather than built up as a String first, so
+// large stats don't need a second copy in memory.
+pub fn write_json<W: Write, T: serde::Serialize + ?Sized>(writer: W, value: &T, pretty: bool) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, value)?;
+    } else {
+        serde_json::to_writer(&mut writer, value)?;
+    }
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Write `value` as YAML into `writer`, for --format yaml. Same fields as the
+// JSON output, from the same serde derives.
+pub fn write_yaml<W: Write, T: serde::Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    serde_yaml::to_writer(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Players' move counts as CSV, for move_analyzer's directory stats and a single
+// game's --extract-moves. Moves are listed in name order.
+pub fn generate_csv_output(players: &[PlayerMoveData], layout: CsvLayout) -> String {
+    if layout == CsvLayout::Wide {
+        return generate_wide_csv_output(players);
+    }
+    
+    let mut output = String::new();
+    output.push_str("port,character,move,count\n");
+    
+    for player in players {
+        for (move_name, count) in player.moves.iter().collect::<BTreeMap<_, _>>() {
+            output.push_str(&format!("{},{},{},{}\n", player.port, csv_field(&player.character), move_name, count));
+        }
+    }
+    
+    output
+}
+
+// One row per player with a column for every move seen in the dataset
+fn generate_wide_csv_output(players: &[PlayerMoveData]) -> String {
+    let mut move_names: Vec<&String> = players.iter().flat_map(|p| p.moves.keys()).collect();
+    move_names.sort();
+    move_names.dedup();
+    
+    let mut output = String::from("port,character");
+    for move_name in &move_names {
+        output.push(',');
+        output.push_str(move_name);
+    }
+    output.push('\n');
+    
+    for player in players {
+        output.push_str(&format!("{},{}", player.port, csv_field(&player.character)));
+        for move_name in &move_names {
+            output.push_str(&format!(",{}", player.moves.get(*move_name).copied().unwrap_or(0)));
+        }
+        output.push('\n');
+    }
+    
+    output
+}
+
+// Quote a CSV value that contains a separator (e.g. grouped "Fox, Falco")
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Process directory of JSON files for aggregated statistics
+pub async fn process_directory_for_moves(directory: &PathBuf, config: &AnalysisConfig) -> Result<MoveStats> {
+    let AnalysisConfig { recursive, strict, group_by, min_games, ref filter, .. } = *config;
+    
+    // Collect the file list first so the files can be parsed in parallel
+    let (paths, duplicates_skipped) = collect_game_files(directory, recursive, filter)?;
+    
+    if strict {
+        // Fail on the first file that doesn't parse
+        let games = paths
+            .par_iter()
+            .filter_map(|path| load_filtered_game(path, filter, group_by).map(|result| result.map_err(|e| anyhow::anyhow!("Failed to parse {:?}: {}", path, e))))
+            .collect::<Result<Vec<_>>>()?;
+        let totals = games.into_iter().fold(DirectoryTotals::default(), DirectoryTotals::add_game);
+        return Ok(MoveStats { duplicates_skipped, ..totals.into_move_stats(group_by, min_games) });
+    }
+    
+    let totals = paths
+        .par_iter()
+        .filter_map(|path| load_filtered_game(path, filter, group_by).map(|result| (path, result)))
+        .fold(DirectoryTotals::default, DirectoryTotals::add_file)
+        .reduce(DirectoryTotals::default, DirectoryTotals::merge);
+    
+    if !totals.errors.is_empty() {
+        warn!("Skipped {} files that could not be parsed", totals.errors.len());
+    }
+    
+    Ok(MoveStats { duplicates_skipped, ..totals.into_move_stats(group_by, min_games) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_moves() {
+        let moves = HashMap::from([
+            ("nair".to_string(), 4),
+            ("shine".to_string(), 12),
+            ("fair".to_string(), 4),
+            ("uair".to_string(), 7),
+            ("bair".to_string(), 4),
+        ]);
+        
+        let ranked = rank_moves(&moves);
+        let names: Vec<&str> = ranked.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["shine", "uair", "bair", "fair", "nair"]);
+        assert_eq!(ranked[0], ("shine".to_string(), 12));
+    }
+
+    #[test]
+    fn test_average_moves_per_game_keeps_fraction() {
+        let totals = DirectoryTotals {
+            total_games: 3,
+            aggregated_moves: HashMap::from([("nair".to_string(), 300), ("shine".to_string(), 149)]),
+            ..Default::default()
+        };
+        
+        let stats = totals.into_move_stats(None, 0);
+        assert_eq!(stats.aggregated_stats["average_moves_per_game"].as_f64(), Some(149.7));
+    }
+
+    #[test]
+    fn test_move_data_serialization() {
+        let mut moves = HashMap::new();
+        moves.insert("nair".to_string(), 10);
+        moves.insert("fair".to_string(), 5);
+        moves.insert("laser".to_string(), 20);
+
+        let player_moves = PlayerMoveData {
+            port: 1,
+            character: "Falco".to_string(),
+            moves,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&player_moves).unwrap();
+        assert!(json.contains("Falco"));
+        assert!(json.contains("nair"));
+        assert!(json.contains("laser"));
+        assert!(json.contains("10"));
+        assert!(json.contains("20"));
+    }
+
+    #[test]
+    fn test_move_stats_structure() {
+        let mut stats_map = HashMap::new();
+        stats_map.insert("most_common_move".to_string(), serde_json::Value::String("laser".to_string()));
+        stats_map.insert("average_moves_per_game".to_string(), serde_json::Value::Number(150.into()));
+
+        let stats = MoveStats {
+            total_games: 3,
+            players: Some(vec![]),
+            teams: vec![],
+            aggregated_stats: stats_map,
+            ranked_moves: vec![],
+            character_profiles: HashMap::new(),
+            character_profiles_per_game: HashMap::new(),
+            failed_files: vec![],
+            duplicates_skipped: 0,
+        };
+
+        let json = serde_json::to_string(&stats).unwrap();
+        assert!(json.contains("total_games"));
+        assert!(json.contains("most_common_move"));
+        assert!(json.contains("laser"));
+        assert!(json.contains("150"));
+    }
+
+
+    fn test_player_moves(port: u8, character: &str, connect_code: Option<&str>, moves: &[(&str, u32)]) -> PlayerMoveData {
+        PlayerMoveData {
+            port,
+            character: character.to_string(),
+            connect_code: connect_code.map(str::to_string),
+            moves: moves.iter().map(|(name, count)| (name.to_string(), *count)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn write_game(dir: &std::path::Path, name: &str, players: Vec<PlayerMoveData>) {
+        let game_data = GameData {
+            player_count: players.len(),
+            duration_frames: 1000,
+            stage: "Battlefield".to_string(),
+            moves: Some(players),
+            ..Default::default()
+        };
+        fs::write(dir.join(name), serde_json::to_string(&game_data).unwrap()).unwrap();
+    }
+
+    fn write_game_json(dir: &std::path::Path, name: &str, character: &str, moves: &[(&str, u32)]) {
+        write_game(dir, name, vec![test_player_moves(1, character, None, moves)]);
+    }
+
+    #[test]
+    fn test_doubles_team_totals() {
+        let player = |port: u8, character: &str, code: &str, team: &str, moves: &[(&str, u32)]| PlayerMoveData {
+            team: Some(team.to_string()),
+            damage_dealt: 50.0,
+            ..test_player_moves(port, character, Some(code), moves)
+        };
+        let game_data = GameData {
+            player_count: 4,
+            duration_frames: 5000,
+            stage: "Battlefield".to_string(),
+            winning_team: Some("Red".to_string()),
+            moves: Some(vec![
+                player(0, "Fox", "AAAA#1", "Red", &[("shine", 10), ("nair", 2)]),
+                player(1, "Marth", "BBBB#2", "Red", &[("fair", 5)]),
+                player(2, "Falco", "CCCC#3", "Blue", &[("shine", 4)]),
+                player(3, "Sheik", "DDDD#4", "Blue", &[("nair", 7)]),
+            ]),
+            ..Default::default()
+        };
+        
+        let stats = DirectoryTotals::default().add_game(game_data).into_move_stats(None, 0);
+        assert_eq!(stats.players().len(), 4);
+        assert_eq!(stats.teams.len(), 2);
+        
+        let red = &stats.teams[0];
+        assert_eq!(red.team, "Red");
+        assert_eq!(red.players, vec!["AAAA#1", "BBBB#2"]);
+        assert_eq!(red.moves.get("shine"), Some(&10));
+        assert_eq!(red.moves.get("fair"), Some(&5));
+        assert_eq!(red.damage_dealt, 100.0);
+        
+        let blue = &stats.teams[1];
+        assert_eq!(blue.team, "Blue");
+        assert_eq!(blue.moves.get("shine"), Some(&4));
+        assert_eq!(blue.moves.get("nair"), Some(&7));
+    }
+
+    #[tokio::test]
+    async fn test_dedup_skips_identical_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("shine", 10)]);
+        fs::copy(dir.path().join("Game_1.json"), dir.path().join("Game_1 copy.json")).unwrap();
+        write_game_json(dir.path(), "Game_2.json", "Fox", &[("shine", 5)]);
+        let directory = dir.path().to_path_buf();
+        
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 3);
+        
+        let filter = GameFilter { dedup: true, ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { group_by: Some(GroupBy::Name), filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+        assert_eq!(stats.duplicates_skipped, 1);
+        assert_eq!(stats.players()[0].moves.get("shine"), Some(&15));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_matches_serial_aggregation() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("nair", 10), ("shine", 30)]);
+        write_game_json(dir.path(), "Game_2.json", "Falco", &[("nair", 5), ("laser", 40)]);
+        write_game_json(dir.path(), "Game_3.json", "Marth", &[("fair", 12)]);
+        write_game_json(dir.path(), "Game_4.json", "Fox", &[("shine", 25), ("uair", 3)]);
+        fs::write(dir.path().join("notes.txt"), "not a game").unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        let parallel = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        
+        let serial = collect_json_files(&directory, false)
+            .unwrap()
+            .iter()
+            .map(|path| (path, load_game_data(path)))
+            .fold(DirectoryTotals::default(), DirectoryTotals::add_file)
+            .into_move_stats(None, 0);
+        
+        assert_eq!(parallel.total_games, 4);
+        assert_eq!(parallel.total_games, serial.total_games);
+        assert_eq!(serde_json::to_value(&parallel).unwrap(), serde_json::to_value(&serial).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_output_is_deterministic() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![test_player_moves(0, "Fox", Some("ZAIN#0"), &[("nair", 10), ("shine", 30), ("upsmash", 2)]), test_player_moves(1, "Marth", None, &[("fair", 12)])]);
+        write_game(dir.path(), "Game_2.json", vec![test_player_moves(0, "Falco", Some("HBOX#0"), &[("laser", 40), ("dair", 5)]), test_player_moves(1, "Fox", Some("MANG#0"), &[("shine", 25), ("uair", 3)])]);
+        
+        let directory = dir.path().to_path_buf();
+        let run = || async {
+            let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+            let mut json = Vec::new();
+            write_json(&mut json, &stats, true).unwrap();
+            (stats, json)
+        };
+        let (stats, first) = run().await;
+        let (_, second) = run().await;
+        assert_eq!(first, second);
+        
+        // Players without a code or name come first, then by code
+        let order: Vec<_> = stats.players().iter().map(|player| player.player_key()).collect();
+        assert_eq!(order, vec!["Port 1 Marth", "HBOX#0", "MANG#0", "ZAIN#0"]);
+    }
+
+    #[test]
+    fn test_jobs_give_identical_output() {
+        let dir = tempfile::tempdir().unwrap();
+        for idx in 0..12 {
+            let character = ["Fox", "Falco", "Marth"][idx % 3];
+            write_game_json(dir.path(), &format!("Game_{}.json", idx), character, &[("nair", idx as u32 + 1), ("shine", 3)]);
+        }
+        let directory = dir.path().to_path_buf();
+        
+        // What --jobs does to the global pool, on a pool of our own
+        let run = |jobs: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build().unwrap();
+            pool.install(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+                runtime.block_on(process_directory_for_moves(&directory, &AnalysisConfig::default())).unwrap()
+            })
+        };
+        let serial = run(1);
+        let parallel = run(4);
+        assert_eq!(serial.total_games, 12);
+        assert_eq!(serde_json::to_value(&serial).unwrap(), serde_json::to_value(&parallel).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_recursive_directory_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let month = dir.path().join("2025-06");
+        let nested = month.join("week-1");
+        fs::create_dir_all(&nested).unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("nair", 1)]);
+        write_game_json(&month, "Game_2.json", "Fox", &[("nair", 1)]);
+        write_game_json(&nested, "Game_3.json", "Fox", &[("nair", 1)]);
+        
+        let directory = dir.path().to_path_buf();
+        let top_level = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(top_level.total_games, 1);
+        
+        let recursive = process_directory_for_moves(&directory, &AnalysisConfig { recursive: true, ..Default::default() }).await.unwrap();
+        assert_eq!(recursive.total_games, 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_recursive_skips_symlink_loops() {
+        let dir = tempfile::tempdir().unwrap();
+        let month = dir.path().join("2025-06");
+        fs::create_dir(&month).unwrap();
+        write_game_json(&month, "Game_1.json", "Fox", &[("nair", 1)]);
+        std::os::unix::fs::symlink(dir.path(), month.join("loop")).unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { recursive: true, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+    }
+
+    #[tokio::test]
+    async fn test_min_games() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![
+            test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 5)]),
+            test_player_moves(1, "Marth", Some("ZAIN#0"), &[("fair", 7)]),
+        ]);
+        write_game(dir.path(), "Game_2.json", vec![
+            test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 3)]),
+            test_player_moves(1, "Falco", Some("HBOX#0"), &[("laser", 9)]),
+        ]);
+        
+        let directory = dir.path().to_path_buf();
+        let config = AnalysisConfig::builder().group_by(Some(GroupBy::Name)).min_games(2).build();
+        let stats = process_directory_for_moves(&directory, &config).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+        assert_eq!(stats.players().len(), 1);
+        assert_eq!(stats.players()[0].connect_code.as_deref(), Some("MANG#0"));
+        assert_eq!(stats.players()[0].games_played, 2);
+        
+        // The one-off players are left out of the aggregates too
+        assert_eq!(stats.ranked_moves, vec![("shine".to_string(), 8)]);
+        assert_eq!(stats.character_profiles.keys().collect::<Vec<_>>(), vec!["Fox"]);
+        assert_eq!(stats.aggregated_stats["most_common_move"], "shine");
+        assert_eq!(stats.aggregated_stats["average_moves_per_game"], 4.0);
+        
+        // Ungrouped, each of a player's games is kept as its own entry
+        let ungrouped = AnalysisConfig { group_by: None, ..config.clone() };
+        let stats = process_directory_for_moves(&directory, &ungrouped).await.unwrap();
+        assert_eq!(stats.players().len(), 2);
+        assert!(stats.players().iter().all(|p| p.connect_code.as_deref() == Some("MANG#0")));
+        assert_eq!(stats.ranked_moves, vec![("shine".to_string(), 8)]);
+        
+        // Without a threshold everyone is kept, with one game each for the one-off players
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { min_games: 0, ..config }).await.unwrap();
+        assert_eq!(stats.players().iter().map(|p| p.games_played).collect::<Vec<_>>(), vec![1, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_group_by_name_merges_games() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![
+            test_player_moves(0, "Fox", Some("MANG#0"), &[("nair", 10), ("shine", 5)]),
+            test_player_moves(1, "Marth", Some("ZAIN#0"), &[("fair", 7)]),
+        ]);
+        write_game(dir.path(), "Game_2.json", vec![
+            test_player_moves(1, "Fox", Some("MANG#0"), &[("nair", 3), ("uair", 2)]),
+            test_player_moves(0, "Falco", None, &[("laser", 9)]),
+        ]);
+        
+        let directory = dir.path().to_path_buf();
+        let ungrouped = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(ungrouped.players().len(), 4);
+        
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { group_by: Some(GroupBy::Name), ..Default::default() }).await.unwrap();
+        assert_eq!(stats.players().len(), 3);
+        
+        let mango = stats.players().iter().find(|p| p.connect_code.as_deref() == Some("MANG#0")).unwrap();
+        assert_eq!(mango.moves.get("nair"), Some(&13));
+        assert_eq!(mango.moves.get("shine"), Some(&5));
+        assert_eq!(mango.moves.get("uair"), Some(&2));
+        
+        // Unnamed players fall back to port + character
+        let unnamed = stats.players().iter().find(|p| p.connect_code.is_none()).unwrap();
+        assert_eq!(unnamed.player_key(), "Port 0 Falco");
+    }
+
+    #[test]
+    fn test_per_game_results() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("nair", 4)]);
+        write_game_json(dir.path(), "Game_2.json", "Marth", &[("fair", 6)]);
+        
+        let mut games = process_directory_per_game(&dir.path().to_path_buf(), &AnalysisConfig::default()).unwrap();
+        assert_eq!(games.len(), 2);
+        
+        games.sort_by(|a, b| a.file.cmp(&b.file));
+        assert!(games[0].file.ends_with("Game_1.json"));
+        assert_eq!(games[0].moves[0].moves.get("nair"), Some(&4));
+        assert!(games[1].file.ends_with("Game_2.json"));
+        assert_eq!(games[1].moves[0].character, "Marth");
+    }
+
+    #[tokio::test]
+    async fn test_character_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("shine", 5)]);
+        write_game_json(dir.path(), "Game_2.json", "Marth", &[("fair", 6)]);
+        write_game(dir.path(), "Game_3.json", vec![
+            test_player_moves(0, "Captain Falcon", Some("HBOX#0"), &[("knee", 3)]),
+            test_player_moves(1, "Fox", Some("MANG#0"), &[("shine", 8)]),
+        ]);
+        
+        let directory = dir.path().to_path_buf();
+        let filter = GameFilter { character: Some("captainfalcon".to_string()), ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter: filter.clone(), ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.players().len(), 2);
+        
+        // Grouped by name, only the ports playing the character are aggregated
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { group_by: Some(GroupBy::Name), filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.players().len(), 1);
+        assert_eq!(stats.players()[0].character, "Captain Falcon");
+        assert_eq!(normalize_name("Captain Falcon"), normalize_name("CAPTAINFALCON"));
+    }
+
+    #[tokio::test]
+    async fn test_port_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![
+            test_player_moves(1, "Captain Falcon", Some("HBOX#0"), &[("knee", 3)]),
+            test_player_moves(2, "Fox", Some("MANG#0"), &[("shine", 8)]),
+        ]);
+        write_game(dir.path(), "Game_2.json", vec![
+            test_player_moves(0, "Marth", Some("ZAIN#0"), &[("fair", 6)]),
+            test_player_moves(2, "Captain Falcon", Some("HBOX#0"), &[("knee", 2)]),
+        ]);
+        // Port 2 isn't in this one
+        write_game_json(dir.path(), "Game_3.json", "Fox", &[("shine", 5)]);
+        
+        let directory = dir.path().to_path_buf();
+        let filter = GameFilter { port: Some(2), ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter: filter.clone(), ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+        assert!(stats.players().iter().all(|p| p.port == 2));
+        assert_eq!(stats.players().len(), 2);
+        
+        // With --character, the port has to be playing it
+        let filter = GameFilter { character: Some("fox".to_string()), ..filter };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.players().len(), 1);
+        assert_eq!(stats.players()[0].moves.get("shine"), Some(&8));
+    }
+
+    #[tokio::test]
+    async fn test_stage_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("shine", 5)]);
+        let dreamland = GameData {
+            player_count: 1,
+            duration_frames: 1000,
+            stage: "Dream Land N64".to_string(),
+            moves: Some(vec![test_player_moves(1, "Fox", None, &[("shine", 7)])]),
+            ..Default::default()
+        };
+        fs::write(dir.path().join("Game_2.json"), serde_json::to_string(&dreamland).unwrap()).unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        let filter = GameFilter { stages: vec!["battlefield".to_string()], ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.players()[0].moves.get("shine"), Some(&5));
+        
+        // Several stages can be allowed at once
+        let filter = GameFilter { stages: vec!["Battlefield".to_string(), "dreamlandn64".to_string()], ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+        assert_eq!(normalize_name("Pokémon Stadium"), "pokemonstadium");
+    }
+
+    #[tokio::test]
+    async fn test_match_type_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, match_type) in [("Game_1.json", "ranked"), ("Game_2.json", "offline"), ("Game_3.json", "ranked")] {
+            write_game_json(dir.path(), name, "Fox", &[("shine", 5)]);
+            let path = dir.path().join(name);
+            let mut game: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            game["match_type"] = match_type.into();
+            fs::write(&path, game.to_string()).unwrap();
+        }
+        // Written before match types were recorded
+        write_game_json(dir.path(), "Game_4.json", "Fox", &[("shine", 5)]);
+        
+        let directory = dir.path().to_path_buf();
+        let filter = GameFilter { match_type: Some("Ranked".to_string()), ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+        
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 4);
+    }
+
+    #[tokio::test]
+    async fn test_date_range_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_20250105T183000.json", "Fox", &[("shine", 1)]);
+        write_game_json(dir.path(), "Game_20250614T201500.json", "Fox", &[("shine", 2)]);
+        write_game_json(dir.path(), "Game_20251120T120000.json", "Fox", &[("shine", 3)]);
+        
+        let directory = dir.path().to_path_buf();
+        let filter = GameFilter {
+            since: NaiveDate::from_ymd_opt(2025, 6, 1),
+            until: NaiveDate::from_ymd_opt(2025, 6, 30),
+            ..Default::default()
+        };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.players()[0].moves.get("shine"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn test_merge_stats_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        fs::create_dir(&first).unwrap();
+        fs::create_dir(&second).unwrap();
+        write_game(&first, "Game_1.json", vec![test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 10)]), test_player_moves(1, "Marth", Some("ZAIN#0"), &[("fair", 4)])]);
+        write_game(&first, "Game_2.json", vec![test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 6)])]);
+        write_game(&second, "Game_3.json", vec![test_player_moves(1, "Falco", Some("MANG#0"), &[("laser", 20)])]);
+        
+        // One session saved as totals, the other as per-game results
+        let first_stats = process_directory_for_moves(&first, &AnalysisConfig::default()).await.unwrap();
+        let second_games = process_directory_per_game(&second, &AnalysisConfig::default()).unwrap();
+        let first_file = dir.path().join("first.json");
+        let second_file = dir.path().join("second.json");
+        fs::write(&first_file, serde_json::to_string(&first_stats).unwrap()).unwrap();
+        fs::write(&second_file, serde_json::to_string(&second_games).unwrap()).unwrap();
+        let files = [first_file, second_file];
+        
+        let merged = merge_stats_files(&files, None).unwrap();
+        assert_eq!(merged.total_games, 3);
+        assert_eq!(merged.players().len(), 4);
+        assert_eq!(merged.ranked_moves, vec![("laser".to_string(), 20), ("shine".to_string(), 16), ("fair".to_string(), 4)]);
+        
+        let grouped = merge_stats_files(&files, Some(GroupBy::Name)).unwrap();
+        assert_eq!(grouped.players().len(), 2);
+        let mango = grouped.players().iter().find(|p| p.player_key() == "MANG#0").unwrap();
+        assert_eq!(mango.character, "Fox, Falco");
+        assert_eq!(mango.moves.get("shine"), Some(&16));
+        assert_eq!(mango.moves.get("laser"), Some(&20));
+        
+        fs::write(dir.path().join("notes.json"), "{\"note\": 1}").unwrap();
+        assert!(merge_stats_files(&[dir.path().join("notes.json")], None).is_err());
+        
+        // A --summary-only file has games but no moves to merge
+        let mut summary = process_directory_for_moves(&first, &AnalysisConfig::default()).await.unwrap();
+        summary.summarize();
+        let summary_file = dir.path().join("summary.json");
+        fs::write(&summary_file, serde_json::to_string(&summary).unwrap()).unwrap();
+        let error = merge_stats_files(&[summary_file], None).err().unwrap();
+        assert!(error.to_string().contains("--summary-only"));
+    }
+
+    #[tokio::test]
+    async fn test_character_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 10), ("nair", 4)]), test_player_moves(1, "Fox", Some("HBOX#0"), &[("shine", 6)])]);
+        write_game(dir.path(), "Game_2.json", vec![test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 8)]), test_player_moves(1, "Marth", Some("ZAIN#0"), &[("fair", 9)])]);
+        
+        // Grouping players doesn't change the per-character figures
+        let stats = process_directory_for_moves(&dir.path().to_path_buf(), &AnalysisConfig { group_by: Some(GroupBy::Name), ..Default::default() }).await.unwrap();
+        assert_eq!(stats.character_profiles.len(), 2);
+        assert_eq!(stats.character_profiles["Fox"], HashMap::from([("shine".to_string(), 24), ("nair".to_string(), 4)]));
+        assert_eq!(stats.character_profiles["Marth"]["fair"], 9);
+        // Fox was played three times, Marth once
+        assert_eq!(stats.character_profiles_per_game["Fox"]["shine"], 8.0);
+        assert_eq!(stats.character_profiles_per_game["Fox"]["nair"], 1.33);
+        assert_eq!(stats.character_profiles_per_game["Marth"]["fair"], 9.0);
+        
+        // Nor does merging grouped stats back in, whose entries cover several games
+        let saved = dir.path().join("stats.json");
+        fs::write(&saved, serde_json::to_string(&stats).unwrap()).unwrap();
+        let merged = merge_stats_files(&[saved], None).unwrap();
+        assert_eq!(merged.character_profiles_per_game["Fox"]["shine"], 8.0);
+        assert_eq!(merged.character_profiles_per_game["Marth"]["fair"], 9.0);
+    }
+
+    #[tokio::test]
+    async fn test_summary_only_omits_players() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("shine", 12)]);
+        let mut stats = process_directory_for_moves(&dir.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        assert!(serde_json::to_value(&stats).unwrap().get("players").is_some());
+        
+        stats.summarize();
+        let json = serde_json::to_value(&stats).unwrap();
+        assert!(json.get("players").is_none());
+        assert_eq!(json["total_games"], 1);
+        assert_eq!(json["aggregated_stats"]["most_common_move"], "shine");
+        
+        // Without --summary-only an empty player list is still written
+        let empty = tempfile::tempdir().unwrap();
+        let stats = process_directory_for_moves(&empty.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(serde_json::to_value(&stats).unwrap()["players"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_write_json_matches_string_output() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 10), ("nair", 4)]), test_player_moves(1, "Falco", None, &[("laser", 6)])]);
+        let stats = process_directory_for_moves(&dir.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        
+        let mut streamed = Vec::new();
+        write_json(&mut streamed, &stats, true).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), format!("{}\n", serde_json::to_string_pretty(&stats).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_yaml_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![test_player_moves(0, "Fox", Some("MANG#0"), &[("shine", 10), ("nair", 4)]), test_player_moves(1, "Falco", None, &[("laser", 6)])]);
+        let stats = process_directory_for_moves(&dir.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        let mut yaml = Vec::new();
+        write_yaml(&mut yaml, &stats).unwrap();
+        let from_yaml: MoveStats = serde_yaml::from_slice(&yaml).unwrap();
+        assert_eq!(serde_json::to_value(&from_yaml).unwrap(), serde_json::to_value(&stats).unwrap());
+        
+        let game: GameData = serde_json::from_str(&fs::read_to_string(dir.path().join("Game_1.json")).unwrap()).unwrap();
+        let mut yaml = Vec::new();
+        write_yaml(&mut yaml, &game).unwrap();
+        let from_yaml: GameData = serde_yaml::from_slice(&yaml).unwrap();
+        assert_eq!(serde_json::to_value(&from_yaml).unwrap(), serde_json::to_value(&game).unwrap());
+    }
+
+    #[test]
+    fn test_compact_json_is_one_line() {
+        let players = vec![test_player_moves(0, "Fox", None, &[("shine", 10), ("nair", 4)])];
+        let mut pretty = Vec::new();
+        write_json(&mut pretty, &players, true).unwrap();
+        let mut compact = Vec::new();
+        write_json(&mut compact, &players, false).unwrap();
+        
+        let pretty = String::from_utf8(pretty).unwrap();
+        let compact = String::from_utf8(compact).unwrap();
+        assert!(pretty.trim_end().contains('\n'));
+        assert!(!compact.trim_end().contains('\n'));
+        assert!(compact.len() < pretty.len());
+        let parsed: Vec<PlayerMoveData> = serde_json::from_str(&compact).unwrap();
+        assert_eq!(parsed[0].moves, players[0].moves);
+    }
+
+    #[tokio::test]
+    async fn test_move_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game(dir.path(), "Game_1.json", vec![test_player_moves(0, "Fox", None, &[("nair", 10), ("shine", 4)])]);
+        fs::write(dir.path().join("aliases.txt"), r#"{"nair": "nAir", "bair": "bAir"}"#).unwrap();
+        let aliases = MoveAliases::load(&dir.path().join("aliases.txt")).unwrap();
+        
+        let mut stats = process_directory_for_moves(&dir.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        aliases.rename_stats(&mut stats);
+        assert_eq!(stats.players()[0].moves, HashMap::from([("nAir".to_string(), 10), ("shine".to_string(), 4)]));
+        assert!(stats.players()[0].move_percentages.contains_key("nAir"));
+        assert_eq!(stats.ranked_moves, vec![("nAir".to_string(), 10), ("shine".to_string(), 4)]);
+        assert_eq!(stats.character_profiles["Fox"]["nAir"], 10);
+        assert_eq!(stats.aggregated_stats["most_common_move"], "nAir");
+        
+        // Moves without an alias keep their name
+        assert_eq!(aliases.name("shine"), "shine");
+        assert!(MoveAliases::load(&dir.path().join("Game_1.json")).is_err());
+    }
+
+    #[test]
+    fn test_moves_per_minute() {
+        // 60 nairs over exactly two minutes
+        let mut fox = PlayerMoveData { frames_played: 7200, ..test_player_moves(0, "Fox", Some("MANG#0"), &[("nair", 60)]) };
+        fox.moves_per_minute = fox.compute_moves_per_minute();
+        assert_eq!(fox.moves_per_minute.get("nair"), Some(&30.0));
+        
+        // Then a one-minute game without any: 60 nairs over three minutes, not
+        // the average of 30/min and 0/min
+        fox.merge(PlayerMoveData { frames_played: 3600, ..test_player_moves(0, "Fox", Some("MANG#0"), &[("fair", 12)]) });
+        assert_eq!(fox.moves_per_minute.get("nair"), Some(&20.0));
+        assert_eq!(fox.moves_per_minute.get("fair"), Some(&4.0));
+        assert!(PlayerMoveData::default().compute_moves_per_minute().is_empty());
+    }
+
+    #[test]
+    fn test_moves_per_stock() {
+        // 24 nairs over a four-stock loss
+        let mut fox = PlayerMoveData { stocks_used: 4, ..test_player_moves(0, "Fox", Some("MANG#0"), &[("nair", 24)]) };
+        assert_eq!(fox.compute_moves_per_stock().get("nair"), Some(&6.0));
+        
+        // Then a win on the last of four stocks with 8 more: 32 over 8 stocks,
+        // not the average of 6 and 2 per stock
+        fox.merge(PlayerMoveData { stocks_used: 4, ..test_player_moves(0, "Fox", Some("MANG#0"), &[("nair", 8)]) });
+        assert_eq!(fox.stocks_used, 8);
+        assert_eq!(fox.compute_moves_per_stock().get("nair"), Some(&4.0));
+        
+        // Only written out when asked for
+        let json = serde_json::to_value(&fox).unwrap();
+        assert!(json.get("moves_per_stock").is_none());
+        fox.moves_per_stock = fox.compute_moves_per_stock();
+        assert_eq!(serde_json::to_value(&fox).unwrap()["moves_per_stock"], serde_json::json!({"nair": 4.0}));
+    }
+
+    #[test]
+    fn test_average_death_percent() {
+        let death = |percent: f32, self_destruct: bool| Death { percent, self_destruct };
+        let mut fox = PlayerMoveData { deaths: vec![death(80.0, false), death(12.0, true)], ..Default::default() };
+        let other_game = PlayerMoveData { deaths: vec![death(120.0, false)], ..Default::default() };
+        
+        // Merging games averages over every death
+        fox.merge(other_game);
+        assert!((fox.average_death_percent - 212.0 / 3.0).abs() < 0.001);
+        assert_eq!(fox.compute_average_death_percent(true), 100.0);
+        assert_eq!(PlayerMoveData::default().compute_average_death_percent(true), 0.0);
+    }
+
+    #[test]
+    fn test_duration() {
+        // 3:42 of game time plus the countdown
+        let seconds = duration_seconds(123 + 222 * 60 + 30);
+        assert_eq!(seconds, 222.5);
+        assert_eq!(duration_display(seconds), "3:42");
+        assert_eq!(duration_display(duration_seconds(123 + 59)), "0:00");
+        // Replays cut off during the countdown
+        assert_eq!(duration_seconds(50), 0.0);
+    }
+
+    #[test]
+    fn test_diff_stats() {
+        let stats = |total_games: u32, players: Vec<PlayerMoveData>| {
+            let mut totals = DirectoryTotals { total_games, ..Default::default() };
+            for player in players {
+                totals.add_player(player);
+            }
+            totals.into_move_stats(None, 0)
+        };
+        let with_rates = |player: PlayerMoveData, aerial_landings: u32, apm: f32| {
+            let mut player = PlayerMoveData { aerial_landings, apm, ..player };
+            player.l_cancel_success_rate = player.compute_l_cancel_success_rate();
+            player
+        };
+        let before = stats(3, vec![
+            with_rates(test_player_moves(0, "Fox", Some("MANG#0"), &[("nair", 100), ("l_cancel", 40), ("shine", 12)]), 50, 300.0),
+            test_player_moves(1, "Marth", Some("ZAIN#0"), &[("fair", 30)]),
+        ]);
+        let after = stats(5, vec![
+            // Now on another port, still matched by connect code
+            with_rates(test_player_moves(2, "Fox", Some("MANG#0"), &[("nair", 145), ("l_cancel", 41), ("shine", 12)]), 50, 290.5),
+            test_player_moves(1, "Falco", Some("HBOX#0"), &[("laser", 20)]),
+        ]);
+        
+        let diff = diff_stats(before, after);
+        assert_eq!(diff.total_games, 2);
+        assert_eq!(diff.players.len(), 1);
+        let mango = &diff.players[0];
+        assert_eq!(mango.player, "MANG#0");
+        assert_eq!(mango.moves.get("nair"), Some(&45));
+        assert_eq!(mango.moves.get("l_cancel"), Some(&1));
+        // Unchanged moves and metrics are left out
+        assert_eq!(mango.moves.get("shine"), None);
+        assert_eq!(mango.metrics.get("l_cancel_success_rate"), Some(&0.02));
+        assert_eq!(mango.metrics.get("apm"), Some(&-9.5));
+        assert_eq!(mango.metrics.get("damage_dealt"), None);
+        assert_eq!(diff.only_before, vec!["ZAIN#0".to_string()]);
+        assert_eq!(diff.only_after, vec!["HBOX#0".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_outdated_schema_is_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("nair", 4)]);
+        write_game_json(dir.path(), "Game_2.json", "Fox", &[("nair", 2)]);
+        // Written before the format was versioned
+        let old = dir.path().join("Game_2.json");
+        let mut json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&old).unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("schema_version");
+        fs::write(&old, json.to_string()).unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        assert_eq!(outdated_game_files(&directory, false).unwrap(), vec![old.clone()]);
+        
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.failed_files.len(), 1);
+        assert_eq!(stats.failed_files[0].path, old);
+        assert!(stats.failed_files[0].error.contains("schema version 0"));
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        write_game_json(dir.path(), "Game_1.json", "Fox", &[("nair", 4)]);
+        fs::write(dir.path().join("Game_2.json"), "{\"player_count\": 2, \"dura").unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.failed_files.len(), 1);
+        assert!(stats.failed_files[0].path.ends_with("Game_2.json"));
+        assert!(!stats.failed_files[0].error.is_empty());
+        
+        // Strict mode fails the whole run instead
+        assert!(process_directory_for_moves(&directory, &AnalysisConfig { strict: true, ..Default::default() }).await.is_err());
+    }
+}
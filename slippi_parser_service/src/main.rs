@@ -1,23 +1,42 @@
 use anyhow::Result;
 use clap::Parser;
-use peppi::io::slippi::read;
-use peppi::game::Player;
+use peppi::io::slippi::{de::Opts, read};
+use peppi::game::{DashBack, End, EndMethod, Player, Port, ShieldDrop, Ucf};
+use peppi::game::immutable::Game;
 use peppi::frame::immutable::Frame;
-use std::path::PathBuf;
-use std::fs::File;
-use std::io::BufReader;
-use std::collections::HashMap;
-use tracing::{info, error};
+use peppi::frame::transpose;
+use flate2::read::GzDecoder;
+use rayon::prelude::*;
+use chrono::{DateTime, NaiveDate};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Cursor, Read, Seek};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::{info, warn, error};
+use slippi_parser_service::{collect_files, date_from_file_name, diff_stats, duration_display, duration_seconds, generate_csv_output, merge_stats_files, outdated_game_files, process_directory_for_moves, process_directory_per_game, write_json, write_yaml, SCHEMA_VERSION, CsvLayout, GameData, GameFilter, MoveAliases, MoveStats, PlayerData, PlayerMoveData, MoveEvent, StatsDiff, WinReason, AnalysisConfig, FrameRange, MIN_COMPLETE_SECONDS, OPENING_RESET_FRAMES};
+use slippi_parser_service::analysis::{character_name, extract_moves_from_frames, form_name, team_color, GameEvents};
+use slippi_parser_service::stages::stage_name;
+
+use cli::{check_games_found, configure_jobs, init_logging, GroupByArg, IcsModeArg};
+
+mod cache;
+mod cli;
+mod server;
+mod watch;
 
 #[derive(Parser)]
 #[command(name = "slippi_parser_service")]
 #[command(about = "A fast Slippi replay file parser using peppi")]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    /// Path to the Slippi replay file (.slp) or directory containing JSON files
-    #[arg(short, long)]
-    file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
     
-    /// Output format (json, text)
+    /// Path to the Slippi replay file (.slp), "-" to read one from stdin, or directory containing JSON files
+    #[arg(short, long, required = true)]
+    file: Option<PathBuf>,
+    
+    /// Output format (json, yaml, text, or csv for a single game's --extract-moves)
     #[arg(long, default_value = "json")]
     format: String,
     
@@ -25,36 +44,375 @@ struct Args {
     #[arg(long)]
     extract_moves: bool,
     
+    /// Read only each replay's start and metadata, skipping frames: a fast catalogue of games without moves, winner or characters played
+    #[arg(long, conflicts_with_all = ["extract_moves", "timeline"])]
+    no_moves: bool,
+    
+    /// Print every move counted in a single replay, techniques included, as an event log of {frame, port, move}, ordered by frame then port
+    #[arg(long)]
+    timeline: bool,
+    
+    /// Directory for cached parse results [default: ~/.cache/slippi_parser_service]
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+    
+    /// Always reparse the replay, neither reading nor writing the cache
+    #[arg(long)]
+    no_cache: bool,
+    
+    /// First frame to extract moves from (0 = first recorded frame); negative counts back from the end, e.g. -1800 for the last 30 seconds
+    #[arg(long, allow_negative_numbers = true)]
+    start_frame: Option<i64>,
+    
+    /// Stop extracting moves before this frame; negative counts back from the end
+    #[arg(long, allow_negative_numbers = true)]
+    end_frame: Option<i64>,
+    
+    /// Frames a combo victim must stay out of hitstun before the combo ends
+    #[arg(long, default_value_t = OPENING_RESET_FRAMES)]
+    combo_reset_frames: u32,
+    
+    /// Leave self-destructs (deaths without a recent hit) out of average_death_percent
+    #[arg(long)]
+    exclude_self_destructs: bool,
+    
+    /// Add moves_per_stock: each move's count divided by the stocks the player used
+    #[arg(long)]
+    per_stock: bool,
+    
+    /// How Ice Climbers' Nana is counted: merged into the player's moves, or split out with a "_nana" suffix
+    #[arg(long, value_enum, default_value = "merge")]
+    ics_mode: IcsModeArg,
+    
     /// Process directory of JSON files for move statistics
     #[arg(long)]
     process_directory: bool,
+    
+    /// Also process JSON files in subdirectories (e.g. month folders)
+    #[arg(long)]
+    recursive: bool,
+    
+    /// Abort on the first file that fails to parse instead of skipping it
+    #[arg(long)]
+    strict: bool,
+    
+    /// Exit with an error when no games were processed, e.g. because the directory path is wrong
+    #[arg(long)]
+    fail_on_empty: bool,
+    
+    /// Threads used to process files in parallel (default: one per logical core)
+    #[arg(long)]
+    jobs: Option<usize>,
+    
+    /// Combine each player's games into a single entry
+    #[arg(long, value_enum)]
+    group_by: Option<GroupByArg>,
+    
+    /// Leave out players seen in fewer than this many games, from the player list and the aggregates
+    #[arg(long, default_value_t = 0)]
+    min_games: u32,
+    
+    /// Only include games where someone plays this character (case and spacing are ignored)
+    #[arg(long)]
+    character: Option<String>,
+    
+    /// Only report this port's player. Ports are 0-based, as in the output's `port` field: P1 is 0, P4 is 3
+    #[arg(long)]
+    port: Option<u8>,
+    
+    /// Only include games on this stage; repeat to allow several
+    #[arg(long = "stage")]
+    stages: Vec<String>,
+    
+    /// Only include games played on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    
+    /// Only include games played on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    
+    /// Only include games of this match type
+    #[arg(long, value_parser = ["ranked", "unranked", "direct", "offline"])]
+    match_type: Option<String>,
+    
+    /// Include games marked incomplete (under a second of play), which are skipped by default
+    #[arg(long)]
+    include_incomplete: bool,
+    
+    /// With --process-directory, output one entry per game instead of aggregated statistics
+    #[arg(long)]
+    per_game: bool,
+    
+    /// Skip byte-identical copies of the same game file
+    #[arg(long)]
+    dedup: bool,
+    
+    /// With --process-directory, leave the per-player entries out of JSON or YAML output, keeping only the totals
+    #[arg(long)]
+    summary_only: bool,
+    
+    /// With --process-directory, first regenerate JSON files in an older schema version from the .slp replay of the same name beside them
+    #[arg(long)]
+    reparse_outdated: bool,
+    
+    /// Parse every .slp replay in the --file directory and write each one's game data to <dir>/<replay name>.json
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+    
+    /// With --output-dir, overwrite JSON files that already exist instead of skipping their replays
+    #[arg(long)]
+    force: bool,
+    
+    /// JSON file mapping move names to the names to print instead, e.g. {"nair": "neutral_air"}
+    #[arg(long)]
+    alias_file: Option<PathBuf>,
+    
+    /// Write JSON on a single line without indentation
+    #[arg(long, overrides_with = "pretty", global = true)]
+    compact: bool,
+    
+    /// Indent JSON output (the default; overrides an earlier --compact)
+    #[arg(long, overrides_with = "compact", global = true)]
+    pretty: bool,
+    
+    /// Log progress with -v, debug output with -vv (default: errors only; a plain RUST_LOG level is used when neither -v nor -q is given)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    
+    /// Don't log anything, not even errors
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Run an HTTP server exposing GET /stats?dir=<path> and GET /game?file=<path>
+    Serve {
+        /// Directory the API may read from; requested paths are resolved against it
+        #[arg(long)]
+        root: PathBuf,
+        
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:3000")]
+        addr: String,
+    },
+    /// Watch a directory and print each new replay's game data as a line of JSON once it has been fully written
+    Watch {
+        /// Directory Slippi writes replays to
+        directory: PathBuf,
+        
+        /// Also watch subdirectories
+        #[arg(long)]
+        recursive: bool,
+        
+        /// Enable move extraction and counting
+        #[arg(long)]
+        extract_moves: bool,
+        
+        /// Milliseconds a replay must go unchanged before it is parsed
+        #[arg(long, default_value_t = 2000)]
+        settle_ms: u64,
+    },
+    /// Check which .slp files in a directory are readable replays, without extracting moves
+    Validate {
+        /// Directory containing .slp files
+        directory: PathBuf,
+        
+        /// Also check replays in subdirectories
+        #[arg(long)]
+        recursive: bool,
+        
+        /// Exit successfully even if some files are invalid
+        #[arg(long)]
+        allow_invalid: bool,
+    },
+    /// Combine previously generated statistics (or --per-game) JSON files into one set of statistics
+    Merge {
+        /// Statistics or per-game JSON files to combine
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        
+        /// Combine each player's games into a single entry
+        #[arg(long, value_enum)]
+        group_by: Option<GroupByArg>,
+    },
+    /// Compare two statistics JSON files and report how each player's moves and metrics changed
+    Diff {
+        /// The earlier statistics
+        before: PathBuf,
+        
+        /// The later statistics
+        after: PathBuf,
+        
+        /// Output format (json, text)
+        #[arg(long, default_value = "json")]
+        format: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-    
     let args = Args::parse();
+    init_logging(args.verbose, args.quiet);
+    configure_jobs(args.jobs)?;
+    let pretty = !args.compact;
+    let aliases = args.alias_file.as_deref().map(MoveAliases::load).transpose()?.unwrap_or_default();
     
     info!("Starting Slippi parser service");
+    match &args.command {
+        Some(Command::Serve { root, addr }) => return server::serve(root, addr).await,
+        Some(Command::Watch { directory, recursive, extract_moves, settle_ms }) => {
+            return watch::watch(directory, *recursive, *extract_moves, std::time::Duration::from_millis(*settle_ms)).await;
+        }
+        Some(Command::Validate { directory, recursive, allow_invalid }) => {
+            let results = validate_directory(directory, *recursive)?;
+            let mut invalid = 0;
+            for (path, result) in &results {
+                match result {
+                    Ok(description) => println!("valid    {} ({})", path.display(), description),
+                    Err(e) => {
+                        invalid += 1;
+                        println!("invalid  {}: {}", path.display(), e);
+                    }
+                }
+            }
+            println!("{} valid, {} invalid", results.len() - invalid, invalid);
+            
+            if invalid > 0 && !allow_invalid {
+                return Err(anyhow::anyhow!("{} invalid replays", invalid));
+            }
+            return Ok(());
+        }
+        Some(Command::Merge { files, group_by }) => {
+            let stats = merge_stats_files(files, group_by.map(Into::into))?;
+            write_json(io::stdout().lock(), &stats, pretty)?;
+            return Ok(());
+        }
+        Some(Command::Diff { before, after, format }) => {
+            let load = |path: &PathBuf| -> Result<MoveStats> {
+                serde_json::from_str(&fs::read_to_string(path)?)
+                    .map_err(|e| anyhow::anyhow!("{:?} is not a move statistics file: {}", path, e))
+            };
+            let diff = diff_stats(load(before)?, load(after)?);
+            match format.as_str() {
+                "json" => write_json(io::stdout().lock(), &diff, pretty)?,
+                "text" => print!("{}", format_diff_text(&diff)),
+                _ => {
+                    error!("Unknown format: {}", format);
+                    return Err(anyhow::anyhow!("Unknown format"));
+                }
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+    // clap requires --file whenever no subcommand is given
+    let file = args.file.as_ref().expect("--file is required");
+    let config = AnalysisConfig::builder()
+        .extract_moves(args.extract_moves)
+        .no_moves(args.no_moves)
+        .ics_mode(args.ics_mode.into())
+        .frame_range(FrameRange { start: args.start_frame, end: args.end_frame })
+        .combo_reset_frames(args.combo_reset_frames)
+        .recursive(args.recursive)
+        .strict(args.strict)
+        .group_by(args.group_by.map(Into::into))
+        .min_games(args.min_games)
+        .filter(GameFilter {
+            character: args.character.clone(),
+            port: args.port,
+            stages: args.stages.clone(),
+            since: args.since,
+            until: args.until,
+            match_type: args.match_type.clone(),
+            include_incomplete: args.include_incomplete,
+            dedup: args.dedup,
+        })
+        .build();
+    
+    if args.process_directory && args.reparse_outdated {
+        let reparsed = reparse_outdated(file, &config).await?;
+        info!("Reparsed {} outdated game files", reparsed);
+    }
+    
+    if let Some(output_dir) = &args.output_dir {
+        info!("Parsing replays in {:?} into {:?}", file, output_dir);
+        let written = parse_directory_to_json(file, output_dir, &config, args.force, pretty).await?;
+        info!("Wrote {} game files", written.len());
+        return Ok(());
+    }
     
-    if args.process_directory {
-        info!("Processing directory for move statistics: {:?}", args.file);
-        match process_directory_for_moves(&args.file).await {
-            Ok(stats) => {
+    if args.process_directory && args.per_game {
+        info!("Processing directory per game: {:?}", file);
+        let mut games = process_directory_per_game(file, &config)?;
+        check_games_found(games.len(), args.fail_on_empty)?;
+        for player_moves in games.iter_mut().flat_map(|game| &mut game.moves) {
+            apply_output_options(player_moves, &args);
+            aliases.rename_player(player_moves);
+        }
+        match args.format.as_str() {
+            "json" => {
+                write_json(io::stdout().lock(), &games, pretty)?;
+            }
+            "yaml" => {
+                write_yaml(io::stdout().lock(), &games)?;
+            }
+            "text" => {
+                for game in &games {
+                    let winner = game.winner
+                        .map(|port| format!("Port {}", port))
+                        .or_else(|| game.winning_team.as_ref().map(|team| format!("{} team", team)))
+                        .unwrap_or_else(|| "none".to_string());
+                    println!("{}: {} players on {}, winner {}", game.file.display(), game.players.len(), game.stage, winner);
+                }
+            }
+            _ => {
+                error!("Unknown format: {}", args.format);
+                return Err(anyhow::anyhow!("Unknown format"));
+            }
+        }
+    } else if args.process_directory {
+        info!("Processing directory for move statistics: {:?}", file);
+        match process_directory_for_moves(file, &config).await {
+            Ok(mut stats) => {
+                check_games_found(stats.total_games as usize, args.fail_on_empty)?;
+                for player_moves in stats.players.iter_mut().flatten() {
+                    apply_output_options(player_moves, &args);
+                }
+                aliases.rename_stats(&mut stats);
                 match args.format.as_str() {
                     "json" => {
-                        let json = serde_json::to_string_pretty(&stats)?;
-                        println!("{}", json);
+                        if args.summary_only {
+                            stats.summarize();
+                        }
+                        write_json(io::stdout().lock(), &stats, pretty)?;
+                    }
+                    "yaml" => {
+                        if args.summary_only {
+                            stats.summarize();
+                        }
+                        write_yaml(io::stdout().lock(), &stats)?;
                     }
                     "text" => {
                         println!("Move Statistics:");
                         println!("  Total games: {}", stats.total_games);
-                        println!("  Players analyzed: {}", stats.players.len());
-                        for player in &stats.players {
+                        println!("  Players analyzed: {}", stats.players().len());
+                        for team in &stats.teams {
+                            println!("  Team {}: {} moves", team.team, team.moves.values().sum::<u32>());
+                        }
+                        for player in stats.players() {
                             println!("    Port {}: {} moves", player.port, player.moves.len());
                         }
+                        if stats.duplicates_skipped > 0 {
+                            println!("  Skipped {} duplicate files", stats.duplicates_skipped);
+                        }
+                        if !stats.failed_files.is_empty() {
+                            println!("  Skipped {} files:", stats.failed_files.len());
+                            for failed in &stats.failed_files {
+                                println!("    {}: {}", failed.path.display(), failed.error);
+                            }
+                        }
                     }
                     _ => {
                         error!("Unknown format: {}", args.format);
@@ -67,26 +425,97 @@ async fn main() -> Result<()> {
                 return Err(e);
             }
         }
+    } else if args.timeline {
+        info!("Building move timeline for: {:?}", file);
+        let mut timeline = if file.as_os_str() == "-" {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            move_timeline(Cursor::new(bytes), &config)?
+        } else {
+            move_timeline(open_replay(file)?, &config)?
+        };
+        if let Some(port) = args.port {
+            timeline.retain(|event| event.port == port);
+        }
+        for event in &mut timeline {
+            event.move_name = aliases.name(&event.move_name).to_string();
+        }
+        match args.format.as_str() {
+            "json" => write_json(io::stdout().lock(), &timeline, pretty)?,
+            "text" => {
+                for event in &timeline {
+                    println!("{:>6}  Port {}  {}", event.frame, event.port, event.move_name);
+                }
+            }
+            _ => {
+                error!("Unknown format: {}", args.format);
+                return Err(anyhow::anyhow!("Unknown format"));
+            }
+        }
     } else {
-        info!("Parsing file: {:?}", args.file);
+        info!("Parsing file: {:?}", file);
         
         // Parse the Slippi file
-        match parse_slippi_file(&args.file, args.extract_moves).await {
-            Ok(game_data) => {
+        let parsed = if file.as_os_str() == "-" {
+            // peppi needs to seek, so stdin is read into memory first
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            parse_slippi_file(Cursor::new(bytes), None, &config).await
+        } else if args.no_cache {
+            parse_slippi_file(open_replay(file)?, Some(file), &config).await
+        } else {
+            let cache = cache::GameCache::new(args.cache_dir.clone().unwrap_or_else(cache::GameCache::default_dir))?;
+            let variant = if config.extract_moves {
+                format!("moves-{:?}-{:?}-{:?}-{}", config.ics_mode, config.frame_range.start, config.frame_range.end, config.combo_reset_frames).to_lowercase()
+            } else if config.no_moves {
+                "metadata".to_string()
+            } else {
+                "basic".to_string()
+            };
+            // Opened inside the future so a cache hit never decompresses a .slp.gz
+            cache.get_or_parse(file, &variant, async { parse_slippi_file(open_replay(file)?, Some(file), &config).await }).await
+        };
+        match parsed {
+            Ok(mut game_data) => {
+                if let Some(port) = args.port {
+                    if !game_data.retain_port(port) {
+                        error!("Nobody played on port {} (ports are 0-based: P1 is 0)", port);
+                        return Err(anyhow::anyhow!("No player on port {}", port));
+                    }
+                }
+                for player_moves in game_data.moves.iter_mut().flatten() {
+                    apply_output_options(player_moves, &args);
+                    aliases.rename_player(player_moves);
+                }
                 match args.format.as_str() {
                     "json" => {
-                        let json = serde_json::to_string_pretty(&game_data)?;
-                        println!("{}", json);
+                        write_json(io::stdout().lock(), &game_data, pretty)?;
+                    }
+                    "yaml" => {
+                        write_yaml(io::stdout().lock(), &game_data)?;
                     }
                     "text" => {
                         println!("Game Data:");
                         println!("  Players: {}", game_data.player_count);
-                        println!("  Duration: {} frames", game_data.duration_frames);
+                        println!("  Duration: {} ({} frames)", game_data.duration_display, game_data.duration_frames);
                         println!("  Stage: {:?}", game_data.stage);
+                        if let Some(winner) = game_data.winner {
+                            match game_data.win_reason {
+                                Some(reason) => println!("  Winner: Port {} ({})", winner, reason),
+                                None => println!("  Winner: Port {}", winner),
+                            }
+                        }
                         if let Some(moves) = &game_data.moves {
                             println!("  Move data extracted for {} players", moves.len());
                         }
                     }
+                    "csv" => {
+                        let Some(moves) = &game_data.moves else {
+                            error!("CSV output lists moves, so it needs --extract-moves");
+                            return Err(anyhow::anyhow!("--format csv requires --extract-moves"));
+                        };
+                        print!("{}", generate_csv_output(moves, CsvLayout::Long));
+                    }
                     _ => {
                         error!("Unknown format: {}", args.format);
                         return Err(anyhow::anyhow!("Unknown format"));
@@ -103,71 +532,89 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct GameData {
-    player_count: usize,
-    duration_frames: u32,
-    stage: String,
-    players: Vec<PlayerData>,
-    moves: Option<Vec<PlayerMoveData>>,
+// Open a replay file for parse_slippi_file
+// Recompute a player's derived stats for --exclude-self-destructs and
+// --per-stock, the same way whether they come from one replay, one game of a
+// directory or a directory's aggregate
+fn apply_output_options(player_moves: &mut PlayerMoveData, args: &Args) {
+    if args.exclude_self_destructs {
+        player_moves.average_death_percent = player_moves.compute_average_death_percent(true);
+    }
+    if args.per_stock {
+        player_moves.moves_per_stock = player_moves.compute_moves_per_stock();
+    }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct PlayerData {
-    port: u8,
-    character: String,
-    stocks: u8,
-    costume: u8,
-    team: Option<String>,
+fn open_replay(file_path: &Path) -> Result<BufReader<Box<dyn ReadSeek>>> {
+    info!("Reading Slippi file from: {:?}", file_path);
+    let file = File::open(file_path)?;
+    
+    // peppi needs to seek, so gzipped replays (.slp.gz) are decompressed into memory
+    if file_path.extension().is_some_and(|ext| ext == "gz") {
+        let mut bytes = Vec::new();
+        GzDecoder::new(file).read_to_end(&mut bytes)?;
+        return Ok(BufReader::new(Box::new(Cursor::new(bytes))));
+    }
+    Ok(BufReader::new(Box::new(file)))
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct PlayerMoveData {
-    port: u8,
-    character: String,
-    moves: HashMap<String, u32>,
-}
+// A replay source: a file, or a decompressed replay in memory
+trait ReadSeek: Read + Seek + Send {}
 
-#[derive(serde::Serialize)]
-struct MoveStats {
-    total_games: u32,
-    players: Vec<PlayerMoveData>,
-    aggregated_stats: HashMap<String, serde_json::Value>,
-}
+impl<T: Read + Seek + Send> ReadSeek for T {}
 
-async fn parse_slippi_file(file_path: &PathBuf, extract_moves: bool) -> Result<GameData> {
-    info!("Reading Slippi file from: {:?}", file_path);
-    
-    // Parse with peppi using the correct API
-    let file = File::open(file_path)?;
-    let mut reader = BufReader::new(file);
-    let game = read(&mut reader, None)?;
+// Parse a replay from any seekable source. `file_path` is only used to date
+// console replays without metadata, and is None when reading from stdin.
+async fn parse_slippi_file(reader: impl Read + Seek, file_path: Option<&Path>, config: &AnalysisConfig) -> Result<GameData> {
+    // Parse with peppi using the correct API, leaving the frames unread when
+    // only the game's metadata is wanted
+    let opts = Opts { skip_frames: config.no_moves, ..Default::default() };
+    let game = read(reader, Some(&opts))?;
     
     info!("Successfully parsed Slippi replay");
     
     // Extract move data if requested
-    let move_data = if extract_moves {
+    let (move_data, events) = if config.extract_moves && !config.no_moves {
         info!("Extracting move data from {} frames", game.frames.len());
-        Some(extract_moves_from_frames(&game.frames, &game.start.players)?)
+        let (moves, events) = extract_moves_from_frames(&game.frames, &game.start.players, game.start.slippi.version, game.start.stage, config.ics_mode, config.frame_range, config.combo_reset_frames);
+        (Some(moves), events)
     } else {
-        None
+        (None, GameEvents::default())
     };
     
     // Extract basic game information
+    let (winner, win_reason, winning_team) = if game.start.is_teams {
+        let winning_team = determine_winning_team(&game);
+        let win_reason = winning_team.as_ref().and(game.end.as_ref()).map(win_reason);
+        (None, win_reason, winning_team)
+    } else {
+        let (winner, win_reason) = determine_winner(&game).unzip();
+        (winner, win_reason, None)
+    };
+    let duration_frames = if config.no_moves {
+        metadata_duration_frames(game.metadata.as_ref()).unwrap_or(0)
+    } else {
+        game.frames.len() as u32
+    };
+    let seconds = duration_seconds(duration_frames);
     let game_data = GameData {
+        schema_version: SCHEMA_VERSION,
         player_count: game.start.players.len(),
-        duration_frames: game.frames.len() as u32,
-        stage: format!("{:?}", game.start.stage),
-        players: game.start.players.iter().map(|player| {
-            PlayerData {
-                port: player.port.into(),
-                character: format!("{:?}", player.character),
-                stocks: player.stocks,
-                costume: player.costume,
-                team: player.team.map(|t| format!("{:?}", t)),
-            }
-        }).collect(),
+        duration_frames,
+        duration_seconds: seconds,
+        duration_display: duration_display(seconds),
+        stage: stage_name(game.start.stage),
+        start_time: game_start_time(game.metadata.as_ref(), file_path),
+        match_type: match_type(game.start.r#match.as_ref().map(|m| m.id.as_str()), game.start.players.iter().any(|p| p.netplay.is_some())),
+        players: game.start.players.iter().map(|player| PlayerData { characters: characters_played(&game.frames, player.port), ..player_data(player) }).collect(),
+        winner,
+        winning_team,
+        win_reason,
+        first_hit_port: events.first_hit_port,
+        last_hit_port: events.last_hit_port,
+        incomplete: seconds < MIN_COMPLETE_SECONDS,
         moves: move_data,
+        stock_events: events.stock_events,
     };
     
     info!("Extracted game data: {} players, {} frames", 
@@ -176,173 +623,337 @@ async fn parse_slippi_file(file_path: &PathBuf, extract_moves: bool) -> Result<G
     Ok(game_data)
 }
 
-// Extract moves from frame data
-fn extract_moves_from_frames(frames: &Frame, players: &[Player]) -> Result<Vec<PlayerMoveData>> {
-    let mut player_moves: Vec<PlayerMoveData> = Vec::new();
+// The game's length from the metadata's lastFrame, for when frames aren't read.
+// Console replays without metadata have none.
+fn metadata_duration_frames(metadata: Option<&serde_json::Map<String, serde_json::Value>>) -> Option<u32> {
+    let last_frame = metadata?.get("lastFrame")?.as_i64()?;
+    u32::try_from(last_frame - peppi::frame::FIRST_INDEX as i64 + 1).ok()
+}
+
+// Every move counted in a replay, in frame then port order, with frames
+// counted from the first recorded frame as in the replay
+fn move_timeline(reader: impl Read + Seek, config: &AnalysisConfig) -> Result<Vec<MoveEvent>> {
+    let game = read(reader, None)?;
+    let (_, events) = extract_moves_from_frames(&game.frames, &game.start.players, game.start.slippi.version, game.start.stage, config.ics_mode, config.frame_range, config.combo_reset_frames);
+    Ok(events.timeline)
+}
+
+// The replay's start time as RFC3339, from the metadata's startAt. Console
+// replays without metadata fall back to the file name, whose local time has no
+// zone and is reported as UTC.
+fn game_start_time(metadata: Option<&serde_json::Map<String, serde_json::Value>>, file_path: Option<&Path>) -> Option<String> {
+    let from_metadata = metadata
+        .and_then(|metadata| metadata.get("startAt"))
+        .and_then(|start_at| start_at.as_str())
+        .and_then(|start_at| DateTime::parse_from_rfc3339(start_at).ok())
+        .map(|start_at| start_at.to_utc());
     
-    // Initialize move counters for each player
-    for player in players {
-        player_moves.push(PlayerMoveData {
-            port: player.port.into(),
-            character: format!("{:?}", player.character),
-            moves: HashMap::new(),
-        });
+    from_metadata
+        .or_else(|| file_path.and_then(date_from_file_name).map(|date| date.and_utc()))
+        .map(|start_time| start_time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+// "ranked", "unranked" or "direct" from a netplay match ID such as
+// "mode.ranked-2025-06-14T20:15:00.00-0", or "offline" when no player has
+// netplay info. Online games in other modes, or from Slippi versions that
+// didn't record match IDs, are None.
+fn match_type(match_id: Option<&str>, online: bool) -> Option<String> {
+    if !online {
+        return Some("offline".to_string());
     }
+    let mode = match_id?.strip_prefix("mode.")?.split('-').next()?;
+    ["ranked", "unranked", "direct"].contains(&mode).then(|| mode.to_string())
+}
+
+// Work out the winning port, and how it won, from the end-of-game block and the
+// final frame. Returns None for ties, no-contests and games that never finished.
+fn determine_winner(game: &Game) -> Option<(u8, WinReason)> {
+    let final_frame = game.frames.len()
+        .checked_sub(1)
+        .map(|frame_idx| game.frames.transpose_one(frame_idx, game.start.slippi.version));
+    winner_from_final_frame(final_frame.as_ref(), game.end.as_ref())
+}
+
+fn winner_from_final_frame(final_frame: Option<&transpose::Frame>, end: Option<&End>) -> Option<(u8, WinReason)> {
+    // No end block means the replay was cut off mid-game
+    let end = end?;
+    let ports: Vec<(u8, u8, f32)> = final_frame?.ports
+        .iter()
+        .map(|p| (p.port.into(), p.leader.post.stocks, p.leader.post.percent))
+        .collect();
     
-    // Iterate through all frames to extract moves
-    for frame_idx in 0..frames.len() {
-        let frame = frames.transpose_one(frame_idx, peppi::io::slippi::Version(3, 0, 0));
-        
-        for (port_idx, port_data) in frame.ports.iter().enumerate() {
-            if let Some(player_data) = player_moves.get_mut(port_idx) {
-                // Analyze pre-frame data for inputs and action states
-                analyze_frame_for_moves(port_data, player_data, frame_idx);
-            }
+    // A player who quits out (LRAS, v2.0+) forfeits to the remaining player
+    if let Some(Some(quitter)) = end.lras_initiator {
+        let quitter: u8 = quitter.into();
+        let remaining: Vec<u8> = ports.iter().map(|p| p.0).filter(|&port| port != quitter).collect();
+        return if remaining.len() == 1 { Some((remaining[0], WinReason::Lras)) } else { None };
+    }
+    
+    // Placements (v3.13+) are the game's own verdict
+    if let Some(placements) = &end.players {
+        let first: Vec<_> = placements.iter().filter(|p| p.placement == 0).collect();
+        if first.len() == 1 {
+            return Some((first[0].port.into(), win_reason(end)));
         }
     }
     
-    Ok(player_moves)
+    if !matches!(end.method, EndMethod::Game | EndMethod::Time | EndMethod::Resolved) {
+        return None;
+    }
+    
+    most_stocks(&ports, end.method).map(|port| (port, win_reason(end)))
 }
 
-// Analyze a single frame for move detection
-fn analyze_frame_for_moves(port_data: &peppi::frame::transpose::PortData, player_data: &mut PlayerMoveData, frame_idx: usize) {
-    let leader = &port_data.leader;
-    
-    // Get action state
-    let action_state = leader.pre.state;
-    let buttons = leader.pre.buttons;
-    
-    // Identify moves based on action state
-    if let Some(move_name) = identify_move_from_action_state(action_state, buttons) {
-        let counter = player_data.moves.entry(move_name).or_insert(0);
-        *counter += 1;
+// A quit-out takes precedence, since the game still records a KO or time-out
+// ending for it
+fn win_reason(end: &End) -> WinReason {
+    if matches!(end.lras_initiator, Some(Some(_))) {
+        WinReason::Lras
+    } else if end.method == EndMethod::Time {
+        WinReason::Timeout
+    } else {
+        WinReason::Ko
+    }
+}
+
+// Most stocks wins; on time-out, equal stocks are decided by lower percent
+fn most_stocks<K: Clone, S: Ord + Copy>(entries: &[(K, S, f32)], method: EndMethod) -> Option<K> {
+    let max_stocks = entries.iter().map(|e| e.1).max()?;
+    let leaders: Vec<&(K, S, f32)> = entries.iter().filter(|e| e.1 == max_stocks).collect();
+    if leaders.len() == 1 {
+        return Some(leaders[0].0.clone());
+    }
+    if method != EndMethod::Time {
+        return None;
     }
     
-    // Additional analysis for special moves and techniques
-    analyze_special_techniques(port_data, player_data, frame_idx);
+    let min_percent = leaders.iter().map(|e| e.2).fold(f32::INFINITY, f32::min);
+    let lowest: Vec<_> = leaders.iter().filter(|e| e.2 == min_percent).collect();
+    if lowest.len() == 1 { Some(lowest[0].0.clone()) } else { None }
 }
 
-// Map action states to move names
-fn identify_move_from_action_state(action_state: u16, _buttons: u32) -> Option<String> {
-    match action_state {
-        // Aerial attacks
-        13 => Some("nair".to_string()),
-        14 => Some("fair".to_string()),
-        15 => Some("bair".to_string()),
-        16 => Some("uair".to_string()),
-        17 => Some("dair".to_string()),
-        
-        // Ground attacks
-        18 => Some("jab".to_string()),
-        19 => Some("ftilt".to_string()),
-        20 => Some("utilt".to_string()),
-        21 => Some("dtilt".to_string()),
-        22 => Some("fsmash".to_string()),
-        23 => Some("usmash".to_string()),
-        24 => Some("dsmash".to_string()),
-        
-        // Special moves
-        25 => Some("neutral_b".to_string()),
-        26 => Some("side_b".to_string()),
-        27 => Some("up_b".to_string()),
-        28 => Some("down_b".to_string()),
-        
-        // Grabs
-        29 => Some("grab".to_string()),
-        30 => Some("dash_attack".to_string()),
-        
-        // Movement
-        31 => Some("jump".to_string()),
-        32 => Some("double_jump".to_string()),
-        
-        _ => None,
+// The winning team color of a teams game, by the same rules as determine_winner
+fn determine_winning_team(game: &Game) -> Option<String> {
+    if !game.start.is_teams {
+        return None;
     }
+    let teams: HashMap<u8, String> = game.start.players
+        .iter()
+        .filter_map(|p| p.team.as_ref().map(|team| (p.port.into(), team_color(team))))
+        .collect();
+    let final_frame = game.frames.len()
+        .checked_sub(1)
+        .map(|frame_idx| game.frames.transpose_one(frame_idx, game.start.slippi.version));
+    winning_team_from_final_frame(final_frame.as_ref(), game.end.as_ref(), &teams)
 }
 
-// Analyze special techniques like wavedash, L-cancel, etc.
-fn analyze_special_techniques(port_data: &peppi::frame::transpose::PortData, player_data: &mut PlayerMoveData, _frame_idx: usize) {
-    let leader = &port_data.leader;
+fn winning_team_from_final_frame(final_frame: Option<&transpose::Frame>, end: Option<&End>, teams: &HashMap<u8, String>) -> Option<String> {
+    let end = end?;
+    let final_frame = final_frame?;
     
-    // Check for wavedash (air dodge into ground within short timeframe)
-    if leader.pre.state == 39 && leader.post.airborne == Some(0) { // Air dodge that ends on ground
-        let counter = player_data.moves.entry("wavedash".to_string()).or_insert(0);
-        *counter += 1;
+    // A player who quits out forfeits for their team
+    if let Some(Some(quitter)) = end.lras_initiator {
+        let quitting_team = teams.get(&quitter.into())?;
+        let remaining: BTreeSet<&String> = teams.values().filter(|&team| team != quitting_team).collect();
+        return if remaining.len() == 1 { remaining.into_iter().next().cloned() } else { None };
     }
     
-    // Check for L-cancel (shield press during landing lag)
-    if leader.pre.buttons & 0x40 != 0 && leader.pre.state >= 40 && leader.pre.state <= 43 { // Shield during landing states
-        let counter = player_data.moves.entry("l_cancel".to_string()).or_insert(0);
-        *counter += 1;
+    // Teammates share a placement, so first place should name a single team
+    if let Some(placements) = &end.players {
+        let first: BTreeSet<&String> = placements.iter()
+            .filter(|p| p.placement == 0)
+            .filter_map(|p| teams.get(&p.port.into()))
+            .collect();
+        if first.len() == 1 {
+            return first.into_iter().next().cloned();
+        }
     }
     
-    // Check for shine (down-B for spacies)
-    if leader.pre.state == 28 && (player_data.character == "Fox" || player_data.character == "Falco") {
-        let counter = player_data.moves.entry("shine".to_string()).or_insert(0);
-        *counter += 1;
+    if !matches!(end.method, EndMethod::Game | EndMethod::Time | EndMethod::Resolved) {
+        return None;
     }
     
-    // Check for laser (neutral-B for Falco)
-    if leader.pre.state == 25 && player_data.character == "Falco" {
-        let counter = player_data.moves.entry("laser".to_string()).or_insert(0);
-        *counter += 1;
+    // A team's stocks and percent are the sums over its players
+    let mut totals: BTreeMap<&String, (u32, f32)> = BTreeMap::new();
+    for port_data in &final_frame.ports {
+        if let Some(team) = teams.get(&port_data.port.into()) {
+            let total = totals.entry(team).or_default();
+            total.0 += port_data.leader.post.stocks as u32;
+            total.1 += port_data.leader.post.percent;
+        }
     }
+    let entries: Vec<(String, u32, f32)> = totals.into_iter().map(|(team, (stocks, percent))| (team.clone(), stocks, percent)).collect();
+    most_stocks(&entries, end.method)
 }
 
-// Process directory of JSON files for aggregated statistics
-async fn process_directory_for_moves(directory: &PathBuf) -> Result<MoveStats> {
-    use std::fs;
-    
-    let mut total_games = 0;
-    let mut all_players: Vec<PlayerMoveData> = Vec::new();
-    let mut aggregated_moves: HashMap<String, u32> = HashMap::new();
-    
-    // Read all JSON files in the directory
-    for entry in fs::read_dir(directory)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.extension().map_or(false, |ext| ext == "json") {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(game_data) = serde_json::from_str::<GameData>(&content) {
-                    total_games += 1;
-                    
-                    if let Some(moves) = game_data.moves {
-                        for player_moves in moves {
-                            // Aggregate moves
-                            for (move_name, count) in &player_moves.moves {
-                                let total_count = aggregated_moves.entry(move_name.clone()).or_insert(0);
-                                *total_count += count;
-                            }
-                            
-                            // Store player data
-                            all_players.push(player_moves);
-                        }
-                    }
-                }
-            }
-        }
+// Describe the controller fixes recorded for a player (UCF dashback and
+// shield drop, or their Arduino equivalents)
+fn controller_name(ucf: &Ucf) -> String {
+    let dash_back = ucf.dash_back.map(|fix| match fix {
+        DashBack::Ucf => "ucf",
+        DashBack::Arduino => "arduino",
+    });
+    let shield_drop = ucf.shield_drop.map(|fix| match fix {
+        ShieldDrop::Ucf => "ucf",
+        ShieldDrop::Arduino => "arduino",
+    });
+    match (dash_back, shield_drop) {
+        (None, None) => "vanilla".to_string(),
+        (Some(a), Some(b)) if a != b => format!("{}+{}", a, b),
+        (Some(fix), _) | (_, Some(fix)) => fix.to_string(),
     }
+}
+
+// Build the output record for a player from the game start block
+fn player_data(player: &Player) -> PlayerData {
+    // Netplay info is only present for online games (Slippi 3.9+)
+    let netplay = player.netplay.as_ref();
     
-    // Create aggregated statistics
-    let mut stats_map = HashMap::new();
-    if let Some(most_common) = aggregated_moves.iter().max_by_key(|(_, count)| *count) {
-        stats_map.insert("most_common_move".to_string(), serde_json::Value::String(most_common.0.clone()));
+    PlayerData {
+        port: player.port.into(),
+        character: character_name(player.character),
+        stocks: player.stocks,
+        costume: player.costume,
+        team: player.team.as_ref().map(team_color),
+        display_name: netplay.map(|n| n.name.to_normalized()),
+        connect_code: netplay.map(|n| n.code.to_normalized()),
+        controller: player.ucf.as_ref().map(controller_name),
+        // Transformations only show in the frames
+        characters: vec![],
     }
+}
+
+// The characters a Zelda/Sheik player took during the game, in the order they
+// first appeared, if they transformed; empty for everyone else
+fn characters_played(frames: &Frame, port: Port) -> Vec<String> {
+    let Some(port_data) = frames.ports.iter().find(|p| p.port == port) else {
+        return vec![];
+    };
+    let mut characters: Vec<String> = Vec::new();
+    for name in port_data.leader.post.character.values().iter().filter_map(|&character| form_name(character)) {
+        if !characters.iter().any(|c| c == name) {
+            characters.push(name.to_string());
+        }
+    }
+    if characters.len() < 2 {
+        characters.clear();
+    }
+    characters
+}
+
+// Regenerate outdated JSON files in a directory from their replays (the .slp or
+// .slp.gz with the same name in the same folder), with moves extracted. Files without a
+// replay are left alone and reported as outdated by the scan. Returns how many
+// files were rewritten. The frame range is ignored so the whole game is covered.
+async fn reparse_outdated(directory: &PathBuf, config: &AnalysisConfig) -> Result<usize> {
+    let config = AnalysisConfig { extract_moves: true, frame_range: FrameRange::default(), ..config.clone() };
+    let mut reparsed = 0;
+    for path in outdated_game_files(directory, config.recursive)? {
+        let Some(replay) = [path.with_extension("slp"), path.with_extension("slp.gz")].into_iter().find(|replay| replay.is_file()) else {
+            warn!("No replay to reparse {:?} from", path);
+            continue;
+        };
+        let game_data = parse_slippi_file(open_replay(&replay)?, Some(&replay), &config).await?;
+        fs::write(&path, serde_json::to_string_pretty(&game_data)?)?;
+        reparsed += 1;
+    }
+    Ok(reparsed)
+}
+
+// Parse every replay in a directory into a JSON file of the same name in
+// `output_dir` (Game_<timestamp>.slp becomes Game_<timestamp>.json), keeping
+// subdirectories when recursive. Replays whose JSON already exists are skipped
+// unless `force` is set. Returns the files written, in replay name order.
+async fn parse_directory_to_json(directory: &PathBuf, output_dir: &Path, config: &AnalysisConfig, force: bool, pretty: bool) -> Result<Vec<PathBuf>> {
+    let mut replays = collect_files(directory, config.recursive, "slp")?;
+    replays.extend(collect_files(directory, config.recursive, "slp.gz")?);
+    replays.sort();
     
-    let total_moves: u32 = aggregated_moves.values().sum();
-    let avg_moves_per_game = if total_games > 0 { total_moves / total_games } else { 0 };
-    stats_map.insert("average_moves_per_game".to_string(), serde_json::Value::Number(avg_moves_per_game.into()));
-    
-    Ok(MoveStats {
-        total_games,
-        players: all_players,
-        aggregated_stats: stats_map,
-    })
+    let mut written = Vec::new();
+    for replay in replays {
+        let relative = replay.strip_prefix(directory)?.to_string_lossy().to_string();
+        let name = relative.strip_suffix(".gz").unwrap_or(&relative).strip_suffix(".slp").unwrap_or(&relative);
+        let output = output_dir.join(format!("{}.json", name));
+        if output.exists() && !force {
+            info!("Skipping {:?}, {:?} already exists", replay, output);
+            continue;
+        }
+        
+        let game_data = match parse_slippi_file(open_replay(&replay)?, Some(&replay), config).await {
+            Ok(game_data) => game_data,
+            Err(e) if !config.strict => {
+                warn!("Failed to parse {:?}: {}", replay, e);
+                continue;
+            }
+            Err(e) => return Err(anyhow::anyhow!("Failed to parse {:?}: {}", replay, e)),
+        };
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_json(File::create(&output)?, &game_data, pretty)?;
+        written.push(output);
+    }
+    Ok(written)
+}
+
+// Check that a replay's header, start block and metadata parse, skipping frame
+// data. Returns a short description of the game on success.
+fn validate_replay(path: &Path) -> Result<String> {
+    let opts = peppi::io::slippi::de::Opts { skip_frames: true, ..Default::default() };
+    let game = read(open_replay(path)?, Some(&opts))?;
+    Ok(format!("{}, {} players", stage_name(game.start.stage), game.start.players.len()))
+}
+
+// Validate every .slp and .slp.gz file in a directory, in file name order
+fn validate_directory(directory: &PathBuf, recursive: bool) -> Result<Vec<(PathBuf, Result<String>)>> {
+    let mut paths = collect_files(directory, recursive, "slp")?;
+    paths.extend(collect_files(directory, recursive, "slp.gz")?);
+    paths.sort();
+    Ok(paths
+        .into_par_iter()
+        .map(|path| {
+            let result = validate_replay(&path);
+            (path, result)
+        })
+        .collect())
+}
+
+// Signed deltas, one per line, with rates shown in percentage points
+fn format_diff_text(diff: &StatsDiff) -> String {
+    let mut out = format!("Games: {:+}\n", diff.total_games);
+    for player in &diff.players {
+        out.push_str(&format!("{}\n", player.player));
+        for (move_name, delta) in &player.moves {
+            out.push_str(&format!("  {}: {:+}\n", move_name, delta));
+        }
+        for (metric, delta) in &player.metrics {
+            if metric.ends_with("_rate") {
+                out.push_str(&format!("  {}: {:+.1}%\n", metric, delta * 100.0));
+            } else {
+                out.push_str(&format!("  {}: {:+.1}\n", metric, delta));
+            }
+        }
+    }
+    if !diff.only_before.is_empty() {
+        out.push_str(&format!("Only in before: {}\n", diff.only_before.join(", ")));
+    }
+    if !diff.only_after.is_empty() {
+        out.push_str(&format!("Only in after: {}\n", diff.only_after.join(", ")));
+    }
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use peppi::io::slippi::Version;
+    use peppi::frame::immutable::Frame;
+    use slippi_parser_service::action_states;
+    use slippi_parser_service::analysis::*;
+    use slippi_parser_service::stages::stage_bounds;
+    use slippi_parser_service::{Combo, Death, IcsMode, PlayerMoveData, StockEvent};
+    use cli::log_filter;
+    use tracing::level_filters::LevelFilter;
 
     #[test]
     fn test_parse_slippi_file_structure() {
@@ -351,7 +962,6 @@ mod tests {
             player_count: 2,
             duration_frames: 1000,
             stage: "Battlefield".to_string(),
-            moves: None,
             players: vec![
                 PlayerData {
                     port: 1,
@@ -359,6 +969,10 @@ mod tests {
                     stocks: 4,
                     costume: 0,
                     team: None,
+                    display_name: None,
+                    connect_code: None,
+                    controller: None,
+                    characters: vec![],
                 },
                 PlayerData {
                     port: 2,
@@ -366,8 +980,13 @@ mod tests {
                     stocks: 4,
                     costume: 1,
                     team: None,
+                    display_name: None,
+                    connect_code: None,
+                    controller: None,
+                    characters: vec![],
                 },
             ],
+            ..Default::default()
         };
 
         // Test serialization
@@ -382,53 +1001,1637 @@ mod tests {
     #[test]
     fn test_move_identification() {
         // Test action state to move name mapping
-        assert_eq!(identify_move_from_action_state(13, 0), Some("nair".to_string()));
-        assert_eq!(identify_move_from_action_state(14, 0), Some("fair".to_string()));
-        assert_eq!(identify_move_from_action_state(15, 0), Some("bair".to_string()));
-        assert_eq!(identify_move_from_action_state(16, 0), Some("uair".to_string()));
-        assert_eq!(identify_move_from_action_state(17, 0), Some("dair".to_string()));
-        assert_eq!(identify_move_from_action_state(18, 0), Some("jab".to_string()));
-        assert_eq!(identify_move_from_action_state(25, 0), Some("neutral_b".to_string()));
-        assert_eq!(identify_move_from_action_state(999, 0), None);
-    }
-
-    #[test]
-    fn test_move_data_serialization() {
-        let mut moves = HashMap::new();
-        moves.insert("nair".to_string(), 10);
-        moves.insert("fair".to_string(), 5);
-        moves.insert("laser".to_string(), 20);
-
-        let player_moves = PlayerMoveData {
-            port: 1,
-            character: "Falco".to_string(),
-            moves,
+        assert_eq!(identify_move_from_action_state(0x41, "Fox"), Some("nair".to_string()));
+        assert_eq!(identify_move_from_action_state(0x42, "Fox"), Some("fair".to_string()));
+        assert_eq!(identify_move_from_action_state(0x43, "Fox"), Some("bair".to_string()));
+        assert_eq!(identify_move_from_action_state(0x44, "Fox"), Some("uair".to_string()));
+        assert_eq!(identify_move_from_action_state(0x45, "Fox"), Some("dair".to_string()));
+        assert_eq!(identify_move_from_action_state(0x2C, "Fox"), Some("jab".to_string()));
+        assert_eq!(identify_move_from_action_state(0x39, "Fox"), Some("dtilt".to_string()));
+        assert_eq!(identify_move_from_action_state(0xD4, "Fox"), Some("grab".to_string()));
+        assert_eq!(identify_move_from_action_state(999, "Fox"), None);
+    }
+
+    #[test]
+    fn test_old_placeholder_states_are_not_moves() {
+        // 13-17 are respawn and walking states in Melee, not aerials
+        for state in 13..=17 {
+            assert_eq!(identify_move_from_action_state(state, "Fox"), None);
+        }
+        assert_eq!(identify_move_from_action_state(0x0E, "Fox"), None); // Wait
+    }
+
+    const TEST_VERSION: Version = Version(3, 16, 0);
+
+    fn synthetic_frame(state: u16) -> transpose::Frame {
+        transpose::Frame {
+            ports: vec![transpose::PortData {
+                port: peppi::game::Port::P1,
+                leader: transpose::Data {
+                    pre: transpose::Pre { state, ..Default::default() },
+                    post: transpose::Post { state, airborne: Some(1), ..Default::default() },
+                },
+                follower: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn empty_player_moves(character: &str) -> Vec<PlayerMoveData> {
+        vec![PlayerMoveData {
+            port: 0,
+            character: character.to_string(),
+            ..Default::default()
+        }]
+    }
+
+    fn test_player(port: peppi::game::Port, character: u8, netplay: Option<(&str, &str)>) -> Player {
+        use peppi::game::{shift_jis::MeleeString, Netplay, PlayerType};
+        
+        Player {
+            port,
+            character,
+            r#type: PlayerType::Human,
+            stocks: 4,
+            costume: 0,
+            team: None,
+            handicap: 9,
+            bitfield: 192,
+            cpu_level: None,
+            damage_start: 0,
+            damage_spawn: 0,
+            offense_ratio: 1.0,
+            defense_ratio: 1.0,
+            model_scale: 1.0,
+            ucf: None,
+            name_tag: None,
+            netplay: netplay.map(|(name, code)| Netplay {
+                name: MeleeString(name.to_string()),
+                code: MeleeString(code.to_string()),
+                suid: None,
+            }),
+        }
+    }
+
+    // A minimal two-player Slippi 2.0 replay on Battlefield, as .slp bytes,
+    // lasting a second past GO!
+    pub(crate) fn test_replay_bytes() -> Vec<u8> {
+        test_replay_with_frames(slippi_parser_service::COUNTDOWN_FRAMES as usize + 60)
+    }
+
+    fn test_replay_with_frames(frame_count: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        peppi::io::slippi::write(&mut bytes, &test_game(frame_count, |_| ())).unwrap();
+        bytes
+    }
+
+    // The game behind test_replay_bytes, with `edit` applied to its frames
+    fn test_game(frame_count: usize, edit: impl FnOnce(&mut peppi::frame::mutable::Frame)) -> Game {
+        use peppi::game::{Port, Quirks, Start};
+        use peppi::io::slippi::Slippi;
+        
+        let version = Version(2, 0, 0);
+        // Raw start block, with every port marked empty (player type 3) until written
+        let mut start_bytes = vec![0; 0x1A2];
+        for port in 0..4 {
+            start_bytes[0x65 + 0x24 * port] = 3;
+        }
+        // Port 1 plays with UCF, port 2 without any fixes
+        let player = |port, character| Player {
+            ucf: Some(match port {
+                Port::P1 => Ucf { dash_back: Some(DashBack::Ucf), shield_drop: Some(ShieldDrop::Ucf) },
+                _ => Ucf { dash_back: None, shield_drop: None },
+            }),
+            name_tag: Some(peppi::game::shift_jis::MeleeString(String::new())),
+            ..test_player(port, character, None)
         };
+        // Frames are only written for ports with valid data
+        let mut frames = mutable_frames_for_ports(version, frame_count, &[Port::P1, Port::P2]);
+        for port_data in &mut frames.ports {
+            port_data.leader.validity = None;
+        }
+        edit(&mut frames);
+        Game {
+            start: Start {
+                slippi: Slippi { version },
+                bitfield: [0; 4],
+                is_raining_bombs: false,
+                is_teams: false,
+                item_spawn_frequency: -1,
+                self_destruct_score: -1,
+                stage: 31,
+                timer: 480,
+                item_spawn_bitfield: [0; 5],
+                damage_ratio: 1.0,
+                players: vec![player(Port::P1, 2), player(Port::P2, 20)],
+                random_seed: 0,
+                bytes: peppi::game::Bytes(start_bytes),
+                is_pal: Some(false),
+                is_frozen_ps: Some(false),
+                scene: None,
+                language: None,
+                r#match: None,
+            },
+            end: Some(End {
+                bytes: peppi::game::Bytes(vec![0; 2]),
+                ..game_end(EndMethod::Game, None)
+            }),
+            frames: frames.into(),
+            metadata: None,
+            gecko_codes: None,
+            hash: None,
+            quirks: Some(Quirks { double_game_end: false }),
+        }
+    }
 
-        let json = serde_json::to_string(&player_moves).unwrap();
-        assert!(json.contains("Falco"));
-        assert!(json.contains("nair"));
-        assert!(json.contains("laser"));
-        assert!(json.contains("10"));
-        assert!(json.contains("20"));
+    #[tokio::test]
+    async fn test_parse_from_reader_matches_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Game_20250614T201500.slp");
+        fs::write(&path, test_replay_bytes()).unwrap();
+        
+        let config = AnalysisConfig::builder().extract_moves(true).build();
+        let from_file = parse_slippi_file(open_replay(&path).unwrap(), Some(&path), &config).await.unwrap();
+        let from_stdin = parse_slippi_file(Cursor::new(test_replay_bytes()), None, &config).await.unwrap();
+        assert_eq!(from_file.stage, "Battlefield");
+        assert_eq!(from_file.player_count, 2);
+        assert_eq!(from_file.match_type.as_deref(), Some("offline"));
+        
+        // Only the file-name date is missing without a path
+        assert_eq!(from_file.start_time.as_deref(), Some("2025-06-14T20:15:00Z"));
+        assert_eq!(from_stdin.start_time, None);
+        let mut from_stdin = serde_json::to_value(&from_stdin).unwrap();
+        from_stdin["start_time"] = serde_json::to_value(&from_file.start_time).unwrap();
+        assert_eq!(from_stdin, serde_json::to_value(&from_file).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_analysis_config_without_cli() {
+        let config = AnalysisConfig::builder()
+            .extract_moves(true)
+            .ics_mode(IcsMode::Split)
+            .frame_range(FrameRange { start: Some(2), end: Some(-2) })
+            .combo_reset_frames(30)
+            .build();
+        assert_eq!(config.frame_range.clamp(10), 2..8);
+        assert_eq!(config.combo_reset_frames, 30);
+        assert!(!config.recursive);
+        
+        let game_data = parse_slippi_file(Cursor::new(test_replay_bytes()), None, &config).await.unwrap();
+        let moves = game_data.moves.unwrap();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].character, "Fox");
+        
+        // Without extraction only the game summary is filled in
+        let summary = parse_slippi_file(Cursor::new(test_replay_bytes()), None, &AnalysisConfig::default()).await.unwrap();
+        assert!(summary.moves.is_none());
+        assert_eq!(summary.stage, "Battlefield");
+    }
+
+    #[tokio::test]
+    async fn test_no_moves_skips_frames() {
+        let mut game = test_game(slippi_parser_service::COUNTDOWN_FRAMES as usize + 60, |_| ());
+        game.metadata = serde_json::json!({ "startAt": "2025-06-14T20:15:00Z", "lastFrame": 59 }).as_object().cloned();
+        let mut bytes = Vec::new();
+        peppi::io::slippi::write(&mut bytes, &game).unwrap();
+        
+        // no_moves wins over extract_moves: there are no frames to extract from
+        let config = AnalysisConfig::builder().extract_moves(true).no_moves(true).build();
+        let game_data = parse_slippi_file(Cursor::new(bytes), None, &config).await.unwrap();
+        assert!(game_data.moves.is_none());
+        assert_eq!(game_data.player_count, 2);
+        assert_eq!(game_data.stage, "Battlefield");
+        assert_eq!(game_data.players.iter().map(|p| p.character.as_str()).collect::<Vec<_>>(), vec!["Fox", "Falco"]);
+        assert_eq!(game_data.start_time.as_deref(), Some("2025-06-14T20:15:00Z"));
+        // The length comes from the metadata, counting the countdown as the frames do
+        assert_eq!(game_data.duration_frames, slippi_parser_service::COUNTDOWN_FRAMES + 60);
+        assert!(game_data.stock_events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_zero_frame_replay_is_incomplete() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AnalysisConfig::builder().extract_moves(true).build();
+        let empty = parse_slippi_file(Cursor::new(test_replay_with_frames(0)), None, &config).await.unwrap();
+        assert!(empty.incomplete);
+        assert_eq!(empty.duration_frames, 0);
+        let complete = parse_slippi_file(Cursor::new(test_replay_bytes()), None, &config).await.unwrap();
+        assert!(!complete.incomplete);
+        fs::write(dir.path().join("Game_1.json"), serde_json::to_string(&empty).unwrap()).unwrap();
+        fs::write(dir.path().join("Game_2.json"), serde_json::to_string(&complete).unwrap()).unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert!(stats.failed_files.is_empty());
+        
+        let filter = GameFilter { include_incomplete: true, ..Default::default() };
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig { filter, ..Default::default() }).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reparse_outdated() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Game_1.slp"), test_replay_bytes()).unwrap();
+        fs::write(dir.path().join("Game_1.json"), r#"{"player_count": 2, "duration_frames": 1, "stage": "Battlefield", "start_time": null, "players": [], "winner": null, "winning_team": null, "moves": null}"#).unwrap();
+        // No replay to regenerate this one from
+        fs::write(dir.path().join("Game_2.json"), "{}").unwrap();
+        
+        let directory = dir.path().to_path_buf();
+        assert_eq!(reparse_outdated(&directory, &AnalysisConfig::default()).await.unwrap(), 1);
+        assert_eq!(outdated_game_files(&directory, false).unwrap(), vec![dir.path().join("Game_2.json")]);
+        
+        let stats = process_directory_for_moves(&directory, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.failed_files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_output_dir_writes_one_json_per_replay() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::write(input.path().join("Game_20250614T201500.slp"), test_replay_bytes()).unwrap();
+        fs::write(input.path().join("Game_20250614T203000.slp"), test_replay_bytes()).unwrap();
+        fs::write(input.path().join("Game_20250614T201500.json"), "{}").unwrap();
+        
+        let directory = input.path().to_path_buf();
+        let config = AnalysisConfig::builder().extract_moves(true).build();
+        let written = parse_directory_to_json(&directory, output.path(), &config, false, true).await.unwrap();
+        assert_eq!(written, vec![
+            output.path().join("Game_20250614T201500.json"),
+            output.path().join("Game_20250614T203000.json"),
+        ]);
+        let stats = process_directory_for_moves(&output.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 2);
+        assert!(stats.failed_files.is_empty());
+        
+        // Existing outputs are left alone unless forced
+        assert!(parse_directory_to_json(&directory, output.path(), &config, false, true).await.unwrap().is_empty());
+        assert_eq!(parse_directory_to_json(&directory, output.path(), &config, true, true).await.unwrap().len(), 2);
     }
 
     #[test]
-    fn test_move_stats_structure() {
-        let mut stats_map = HashMap::new();
-        stats_map.insert("most_common_move".to_string(), serde_json::Value::String("laser".to_string()));
-        stats_map.insert("average_moves_per_game".to_string(), serde_json::Value::Number(150.into()));
+    fn test_validate_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let replay = test_replay_bytes();
+        fs::write(dir.path().join("Game_1.slp"), &replay).unwrap();
+        fs::write(dir.path().join("Game_2.slp"), &replay[..40]).unwrap();
+        fs::write(dir.path().join("Game_3.slp"), b"not a replay").unwrap();
+        fs::write(dir.path().join("Game_1.json"), "{}").unwrap();
+        
+        let results = validate_directory(&dir.path().to_path_buf(), false).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].1.as_ref().unwrap(), "Battlefield, 2 players");
+        assert!(results[1].1.is_err());
+        assert!(results[2].1.is_err());
+    }
 
-        let stats = MoveStats {
-            total_games: 3,
-            players: vec![],
-            aggregated_stats: stats_map,
-        };
+    #[tokio::test]
+    async fn test_gzipped_replay_matches_original() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("Game_1.slp");
+        let gzipped = dir.path().join("Game_2.slp.gz");
+        fs::write(&plain, test_replay_bytes()).unwrap();
+        let mut encoder = GzEncoder::new(File::create(&gzipped).unwrap(), Compression::default());
+        encoder.write_all(&test_replay_bytes()).unwrap();
+        encoder.finish().unwrap();
+        
+        let config = AnalysisConfig::builder().extract_moves(true).build();
+        let from_plain = parse_slippi_file(open_replay(&plain).unwrap(), None, &config).await.unwrap();
+        let from_gzipped = parse_slippi_file(open_replay(&gzipped).unwrap(), None, &config).await.unwrap();
+        assert_eq!(serde_json::to_value(&from_plain).unwrap(), serde_json::to_value(&from_gzipped).unwrap());
+        
+        // The validator picks up compressed replays too
+        let results = validate_directory(&dir.path().to_path_buf(), false).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+    }
 
-        let json = serde_json::to_string(&stats).unwrap();
-        assert!(json.contains("total_games"));
-        assert!(json.contains("most_common_move"));
-        assert!(json.contains("laser"));
-        assert!(json.contains("150"));
+    #[test]
+    fn test_player_netplay_info() {
+        // Melee stores the '#' in connect codes as a full-width character
+        let online = player_data(&test_player(peppi::game::Port::P1, 2, Some(("Mang0", "MANG＃0"))));
+        assert_eq!(online.character, "Fox");
+        assert_eq!(online.display_name.as_deref(), Some("Mang0"));
+        assert_eq!(online.connect_code.as_deref(), Some("MANG#0"));
+        
+        // Console/offline replays have no netplay block
+        let offline = player_data(&test_player(peppi::game::Port::P2, 20, None));
+        assert_eq!(offline.display_name, None);
+        assert_eq!(offline.connect_code, None);
+        
+        let json = serde_json::to_string(&online).unwrap();
+        assert!(json.contains("\"connect_code\":\"MANG#0\""));
+    }
+
+    #[tokio::test]
+    async fn test_controller_fixes() {
+        let game_data = parse_slippi_file(Cursor::new(test_replay_bytes()), None, &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(game_data.players[0].controller.as_deref(), Some("ucf"));
+        assert_eq!(game_data.players[1].controller.as_deref(), Some("vanilla"));
+        
+        let mixed = Ucf { dash_back: Some(DashBack::Ucf), shield_drop: Some(ShieldDrop::Arduino) };
+        assert_eq!(controller_name(&mixed), "ucf+arduino");
+        let arduino = Ucf { dash_back: Some(DashBack::Arduino), shield_drop: None };
+        assert_eq!(controller_name(&arduino), "arduino");
+        
+        // Replays from before Slippi 1.0 don't record it
+        assert_eq!(player_data(&test_player(peppi::game::Port::P1, 2, None)).controller, None);
+    }
+
+    #[test]
+    fn test_character_name() {
+        assert_eq!(character_name(0), "Captain Falcon");
+        assert_eq!(character_name(2), "Fox");
+        assert_eq!(character_name(14), "Ice Climbers");
+        assert_eq!(character_name(20), "Falco");
+        assert_eq!(character_name(22), "Dr. Mario");
+        assert_eq!(character_name(99), "Unknown(99)");
+    }
+
+    #[test]
+    fn test_character_specific_specials() {
+        assert_eq!(identify_move_from_action_state(0x163, "Fox"), Some("firefox".to_string()));
+        assert_eq!(identify_move_from_action_state(0x163, "Falco"), Some("firebird".to_string()));
+        assert_eq!(identify_move_from_action_state(0x167, "Fox"), Some("shine".to_string()));
+        assert_eq!(identify_move_from_action_state(0x16B, "Falco"), Some("shine".to_string()));
+        assert_eq!(identify_move_from_action_state(0x155, "Falco"), Some("laser".to_string()));
+        assert_eq!(identify_move_from_action_state(0x15C, "Fox"), Some("illusion".to_string()));
+        assert_eq!(identify_move_from_action_state(0x15C, "Falco"), Some("phantasm".to_string()));
+        
+        // Same state, different character
+        assert_eq!(identify_move_from_action_state(0x159, "Fox"), Some("laser".to_string()));
+        assert_eq!(identify_move_from_action_state(0x159, "Marth"), Some("shield_breaker".to_string()));
+        assert_eq!(identify_move_from_action_state(0x15D, "Marth"), Some("dancing_blade".to_string()));
+        assert_eq!(identify_move_from_action_state(0x16F, "Marth"), Some("dolphin_slash".to_string()));
+        assert_eq!(identify_move_from_action_state(0x171, "Marth"), Some("counter".to_string()));
+        
+        // Characters without a table fall back to generic names
+        assert_eq!(identify_move_from_action_state(0x155, "Peach"), Some("neutral_b".to_string()));
+        assert_eq!(identify_move_from_action_state(0x167, "Peach"), Some("down_b".to_string()));
+        
+        // Common moves are the same regardless of character
+        assert_eq!(identify_move_from_action_state(0x41, "Marth"), Some("nair".to_string()));
+    }
+
+    // Build an all-null single-port frame array in the layout of the given version
+    fn frames_with_version(version: Version, count: usize) -> Frame {
+        mutable_frames(version, count).into()
+    }
+
+    fn mutable_frames(version: Version, count: usize) -> peppi::frame::mutable::Frame {
+        mutable_frames_for_ports(version, count, &[peppi::game::Port::P1])
+    }
+
+    fn mutable_frames_for_ports(version: Version, count: usize, ports: &[peppi::game::Port]) -> peppi::frame::mutable::Frame {
+        use peppi::frame::{mutable, PortOccupancy};
+        
+        let ports: Vec<PortOccupancy> = ports.iter().map(|&port| PortOccupancy { port, follower: false }).collect();
+        let mut frames = mutable::Frame::with_capacity(count, version, &ports);
+        for frame_idx in 0..count {
+            frames.id.push(Some(peppi::frame::FIRST_INDEX + frame_idx as i32));
+            for port_data in &mut frames.ports {
+                port_data.leader.push_null(version);
+            }
+            if let Some(start) = frames.start.as_mut() {
+                start.push_null(version);
+            }
+            if let Some(end) = frames.end.as_mut() {
+                end.push_null(version);
+            }
+            if let Some(item_offset) = frames.item_offset.as_mut() {
+                item_offset.try_push(0).unwrap();
+            }
+        }
+        frames
+    }
+
+    fn final_frame(ports: &[(peppi::game::Port, u8, f32)]) -> transpose::Frame {
+        transpose::Frame {
+            ports: ports.iter().map(|&(port, stocks, percent)| transpose::PortData {
+                port,
+                leader: transpose::Data {
+                    pre: Default::default(),
+                    post: transpose::Post { stocks, percent, ..Default::default() },
+                },
+                follower: None,
+            }).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn game_end(method: EndMethod, lras_initiator: Option<peppi::game::Port>) -> End {
+        End {
+            method,
+            bytes: peppi::game::Bytes(vec![]),
+            lras_initiator: Some(lras_initiator),
+            players: None,
+        }
+    }
+
+    #[test]
+    fn test_determine_winner() {
+        use peppi::game::Port;
+        
+        let frame = final_frame(&[(Port::P1, 2, 45.0), (Port::P2, 0, 120.0)]);
+        assert_eq!(winner_from_final_frame(Some(&frame), Some(&game_end(EndMethod::Game, None))), Some((0, WinReason::Ko)));
+        
+        // Time-out with more stocks wins regardless of percent
+        let frame = final_frame(&[(Port::P1, 1, 150.0), (Port::P2, 2, 10.0)]);
+        assert_eq!(winner_from_final_frame(Some(&frame), Some(&game_end(EndMethod::Time, None))), Some((1, WinReason::Timeout)));
+        
+        // Time-out with equal stocks goes to the lower percent; exact ties have no winner
+        let frame = final_frame(&[(Port::P1, 2, 90.0), (Port::P2, 2, 30.0)]);
+        assert_eq!(winner_from_final_frame(Some(&frame), Some(&game_end(EndMethod::Time, None))), Some((1, WinReason::Timeout)));
+        let frame = final_frame(&[(Port::P1, 2, 30.0), (Port::P2, 2, 30.0)]);
+        assert_eq!(winner_from_final_frame(Some(&frame), Some(&game_end(EndMethod::Time, None))), None);
+        
+        // Incomplete replays have no end block
+        assert_eq!(winner_from_final_frame(Some(&frame), None), None);
+    }
+
+    #[test]
+    fn test_lras_win_reason() {
+        use peppi::game::Port;
+        
+        // Quitting out forfeits, even while ahead
+        let frame = final_frame(&[(Port::P1, 3, 0.0), (Port::P2, 1, 80.0)]);
+        assert_eq!(winner_from_final_frame(Some(&frame), Some(&game_end(EndMethod::NoContest, Some(Port::P1)))), Some((1, WinReason::Lras)));
+        
+        // Also on the timer, and in teams games
+        let end = game_end(EndMethod::Time, Some(Port::P2));
+        assert_eq!(winner_from_final_frame(Some(&frame), Some(&end)), Some((0, WinReason::Lras)));
+        assert_eq!(win_reason(&end), WinReason::Lras);
+    }
+
+    #[test]
+    fn test_winning_team() {
+        use peppi::game::Port;
+        
+        let teams: HashMap<u8, String> = HashMap::from([
+            (0, "Red".to_string()),
+            (1, "Red".to_string()),
+            (2, "Blue".to_string()),
+            (3, "Blue".to_string()),
+        ]);
+        
+        // Red still has a player standing
+        let frame = final_frame(&[(Port::P1, 0, 0.0), (Port::P2, 1, 80.0), (Port::P3, 0, 0.0), (Port::P4, 0, 0.0)]);
+        assert_eq!(winning_team_from_final_frame(Some(&frame), Some(&game_end(EndMethod::Game, None)), &teams), Some("Red".to_string()));
+        
+        // Team stocks are summed, so 1 + 2 ties 3 + 0 and the lower total percent wins at time-out
+        let frame = final_frame(&[(Port::P1, 1, 50.0), (Port::P2, 2, 40.0), (Port::P3, 3, 20.0), (Port::P4, 0, 0.0)]);
+        assert_eq!(winning_team_from_final_frame(Some(&frame), Some(&game_end(EndMethod::Time, None)), &teams), Some("Blue".to_string()));
+        
+        // Quitting out forfeits for the whole team
+        assert_eq!(winning_team_from_final_frame(Some(&frame), Some(&game_end(EndMethod::NoContest, Some(Port::P3))), &teams), Some("Red".to_string()));
+    }
+
+    #[test]
+    fn test_edgeguards() {
+        use action_states::*;
+        use peppi::game::Port;
+        use slippi_parser_service::Edgeguards;
+        
+        // Port 1 stays on stage while port 2 is knocked past the ledge
+        let frame = |attacker_state: u16, victim_state: u16, victim_x: f32, victim_stocks: u8| {
+            let data = |state: u16, x: f32, stocks: u8, airborne: u8| transpose::Data {
+                pre: transpose::Pre { state, ..Default::default() },
+                post: transpose::Post { state, position: transpose::Position { x, y: 10.0 }, stocks, airborne: Some(airborne), ..Default::default() },
+            };
+            transpose::Frame {
+                ports: vec![
+                    transpose::PortData { port: Port::P1, leader: data(attacker_state, 20.0, 4, 0), follower: None },
+                    transpose::PortData { port: Port::P2, leader: data(victim_state, victim_x, victim_stocks, 1), follower: None },
+                ],
+                ..Default::default()
+            }
+        };
+        let mut frames = vec![
+            // Attacks while the opponent is on stage don't count
+            frame(WAIT, FALL, 30.0, 4),
+            frame(ATTACK_LW3, FALL, 30.0, 4),
+            // Offstage: a ledge grab and then a bair are one attempt
+            frame(WAIT, FALL, 90.0, 4),
+            frame(CLIFF_CATCH, FALL, 95.0, 4),
+            frame(CLIFF_WAIT, FALL, 100.0, 4),
+            frame(ATTACK_AIR_B, FALL, 110.0, 4),
+            // The opponent dies off the side
+            frame(FALL, FALL, 230.0, 3),
+        ];
+        // Next stock: chased offstage with a fair, but they make it back to the ledge and stage
+        frames.extend([
+            frame(WAIT, FALL, 90.0, 3),
+            frame(ATTACK_AIR_F, FALL, 90.0, 3),
+            frame(FALL, CLIFF_CATCH, 70.0, 3),
+            frame(FALL, WAIT, 40.0, 3),
+        ]);
+        frames.extend((0..=EDGEGUARD_KILL_WINDOW).map(|_| frame(WAIT, WAIT, 40.0, 3)));
+        // A stock lost long after getting back isn't an edgeguard
+        frames.push(frame(WAIT, WAIT, 40.0, 2));
+        
+        let mut player_moves = vec![
+            PlayerMoveData { port: 0, character: "Fox".to_string(), ..Default::default() },
+            PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() },
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, stage_bounds(31), IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].edgeguards, Edgeguards { attempts: 2, successes: 1 });
+        assert_eq!(player_moves[1].edgeguards, Edgeguards::default());
+    }
+
+    #[test]
+    fn test_death_percents() {
+        use action_states::*;
+        
+        let frame = |state: u16, percent: f32, stocks: u8| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.percent = percent;
+            frame.ports[0].leader.post.stocks = stocks;
+            frame
+        };
+        let mut frames: Vec<transpose::Frame> = Vec::new();
+        // Launched at 80% and 120%, dying in tumble
+        frames.extend((0..10).map(|_| frame(WAIT, 0.0, 4)));
+        frames.extend((0..10).map(|_| frame(DAMAGE_FLY_ROLL, 80.0, 4)));
+        frames.extend((0..10).map(|_| frame(WAIT, 0.0, 3)));
+        frames.extend((0..10).map(|_| frame(DAMAGE_FLY_ROLL, 120.0, 3)));
+        // Then walking off at 30% with nobody near
+        frames.extend((0..60).map(|_| frame(FALL, 30.0, 2)));
+        frames.push(frame(WAIT, 0.0, 1));
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].deaths, vec![
+            Death { percent: 80.0, self_destruct: false },
+            Death { percent: 120.0, self_destruct: false },
+            Death { percent: 30.0, self_destruct: true },
+        ]);
+        assert!((player_moves[0].average_death_percent - 230.0 / 3.0).abs() < 0.001);
+        assert_eq!(player_moves[0].compute_average_death_percent(true), 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let stats = process_directory_for_moves(&dir.path().to_path_buf(), &AnalysisConfig::default()).await.unwrap();
+        assert_eq!(stats.total_games, 0);
+        
+        // Only an error when asked for
+        assert!(check_games_found(stats.total_games as usize, false).is_ok());
+        assert!(check_games_found(stats.total_games as usize, true).is_err());
+        assert!(check_games_found(1, true).is_ok());
+    }
+
+    #[test]
+    fn test_log_filter() {
+        let level = |verbose, quiet, rust_log| log_filter(verbose, quiet, rust_log).max_level_hint();
+        assert_eq!(level(0, false, None), Some(LevelFilter::ERROR));
+        assert_eq!(level(1, false, None), Some(LevelFilter::INFO));
+        assert_eq!(level(2, false, None), Some(LevelFilter::DEBUG));
+        assert_eq!(level(5, false, None), Some(LevelFilter::TRACE));
+        assert_eq!(level(0, true, None), Some(LevelFilter::OFF));
+        
+        // RUST_LOG only applies without -v or -q
+        assert_eq!(level(0, false, Some("debug")), Some(LevelFilter::DEBUG));
+        assert_eq!(level(1, false, Some("trace")), Some(LevelFilter::INFO));
+        assert_eq!(level(0, true, Some("debug")), Some(LevelFilter::OFF));
+        assert_eq!(level(0, false, Some("not a filter=")), Some(LevelFilter::ERROR));
+        
+        // Per-module directives are kept as written
+        let filter = log_filter(0, false, Some("slippi_parser_service=debug,peppi=warn"));
+        assert_eq!(filter.max_level_hint(), Some(LevelFilter::DEBUG));
+        let directives = filter.to_string();
+        assert!(directives.contains("slippi_parser_service=debug") && directives.contains("peppi=warn"));
+    }
+
+    #[test]
+    fn test_output_options_apply_to_per_game_output() {
+        let mut player_moves = PlayerMoveData {
+            moves: HashMap::from([("nair".to_string(), 4)]),
+            deaths: vec![
+                Death { percent: 120.0, self_destruct: false },
+                Death { percent: 30.0, self_destruct: true },
+            ],
+            stocks_used: 2,
+            average_death_percent: 75.0,
+            ..Default::default()
+        };
+        let args = Args::parse_from(["slippi_parser_service", "-f", "parsedgames", "--process-directory", "--per-game", "--exclude-self-destructs", "--per-stock"]);
+        apply_output_options(&mut player_moves, &args);
+        assert_eq!(player_moves.average_death_percent, 120.0);
+        assert_eq!(player_moves.moves_per_stock.get("nair"), Some(&2.0));
+        
+        // Left as extracted without the flags
+        let mut untouched = PlayerMoveData { average_death_percent: 75.0, ..player_moves };
+        untouched.moves_per_stock.clear();
+        apply_output_options(&mut untouched, &Args::parse_from(["slippi_parser_service", "-f", "parsedgames", "--process-directory", "--per-game"]));
+        assert_eq!(untouched.average_death_percent, 75.0);
+        assert!(untouched.moves_per_stock.is_empty());
+    }
+
+    #[test]
+    fn test_self_destructs() {
+        use action_states::*;
+        use peppi::game::Port;
+        
+        let frame = |falcon_state: u16, falcon_stocks: u8, fox_state: u16, fox_stocks: u8| {
+            let data = |state: u16, stocks: u8| transpose::Data {
+                pre: transpose::Pre { state, ..Default::default() },
+                post: transpose::Post { state, stocks, percent: 50.0, ..Default::default() },
+            };
+            transpose::Frame {
+                ports: vec![
+                    transpose::PortData { port: Port::P1, leader: data(falcon_state, falcon_stocks), follower: None },
+                    transpose::PortData { port: Port::P2, leader: data(fox_state, fox_stocks), follower: None },
+                ],
+                ..Default::default()
+            }
+        };
+        let mut frames: Vec<transpose::Frame> = Vec::new();
+        // Fox walks off with nobody near: a self-destruct
+        frames.extend((0..60).map(|_| frame(WAIT, 4, FALL, 4)));
+        // Then is launched and KO'd
+        frames.extend((0..10).map(|_| frame(WAIT, 4, DAMAGE_FLY_ROLL, 3)));
+        frames.extend((0..60).map(|_| frame(WAIT, 4, WAIT, 2)));
+        // Then Falcon takes Fox offstage with side-B, Falcon's stock going a few frames first
+        frames.extend((0..20).map(|_| frame(SPECIAL_START, 4, COMMAND_GRABBED_START, 2)));
+        frames.extend((0..3).map(|_| frame(WAIT, 3, COMMAND_GRABBED_START, 2)));
+        frames.push(frame(WAIT, 3, WAIT, 1));
+        
+        let mut player_moves = vec![
+            PlayerMoveData { port: 0, character: "Captain Falcon".to_string(), ..Default::default() },
+            PlayerMoveData { port: 1, character: "Fox".to_string(), ..Default::default() },
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        let self_destructs = |player: &PlayerMoveData| player.deaths.iter().map(|death| death.self_destruct).collect::<Vec<_>>();
+        assert_eq!(self_destructs(&player_moves[1]), vec![true, false, false]);
+        assert_eq!(player_moves[1].self_destructs, 1);
+        // Falcon traded the stock rather than self-destructing
+        assert_eq!(self_destructs(&player_moves[0]), vec![false]);
+        assert_eq!(player_moves[0].self_destructs, 0);
+    }
+
+    #[test]
+    fn test_attack_hits_and_whiffs() {
+        use action_states::*;
+        use peppi::game::Port;
+        use slippi_parser_service::MoveOutcome;
+        
+        let frame = |attacker_state: u16, victim_state: u16, victim_percent: f32| {
+            let data = |state: u16, percent: f32, last_hit_by: u8| transpose::Data {
+                pre: transpose::Pre { state, ..Default::default() },
+                post: transpose::Post { state, percent, last_hit_by, stocks: 4, ..Default::default() },
+            };
+            transpose::Frame {
+                ports: vec![
+                    transpose::PortData { port: Port::P1, leader: data(attacker_state, 0.0, 6), follower: None },
+                    transpose::PortData { port: Port::P2, leader: data(victim_state, victim_percent, 0), follower: None },
+                ],
+                ..Default::default()
+            }
+        };
+        let frames = vec![
+            // The first fsmash is spaced too far and whiffs
+            frame(WAIT, WAIT, 0.0),
+            frame(ATTACK_S4_S, WAIT, 0.0),
+            frame(ATTACK_S4_S, WAIT, 0.0),
+            frame(WAIT, WAIT, 0.0),
+            // The second one sends them into hitstun
+            frame(ATTACK_S4_S, WAIT, 0.0),
+            frame(ATTACK_S4_S, DAMAGE_FLY_ROLL, 18.0),
+            frame(WAIT, DAMAGE_FLY_ROLL, 18.0),
+        ];
+        
+        let mut player_moves = vec![
+            PlayerMoveData { port: 0, character: "Marth".to_string(), ..Default::default() },
+            PlayerMoveData { port: 1, character: "Fox".to_string(), ..Default::default() },
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("fsmash"), Some(&2));
+        assert_eq!(player_moves[0].move_outcomes.get("fsmash"), Some(&MoveOutcome { hits: 1, whiffs: 1 }));
+        assert!(player_moves[1].move_outcomes.is_empty());
+    }
+
+    #[test]
+    fn test_di_classification() {
+        use action_states::*;
+        
+        // Launched up and to the right at 30 degrees
+        let (knockback_x, knockback_y) = (30f32.to_radians().cos() * 3.0, 30f32.to_radians().sin() * 3.0);
+        let hit = |hitlag: f32, stick_x: f32, stick_y: f32| {
+            let mut frame = synthetic_frame(DAMAGE_FLY_ROLL);
+            let leader = &mut frame.ports[0].leader;
+            leader.pre.joystick = transpose::Position { x: stick_x, y: stick_y };
+            leader.post.hitlag = Some(hitlag);
+            leader.post.velocities = Some(transpose::Velocities { knockback_x, knockback_y, ..Default::default() });
+            frame
+        };
+        let frames = vec![
+            synthetic_frame(WAIT),
+            // Two SDI inputs during hitlag (right, then up-right; holding isn't another)
+            hit(3.0, 1.0, 0.0),
+            hit(2.0, 0.7, 0.7),
+            hit(1.0, 0.7, 0.7),
+            // Held up and to the left, perpendicular to the launch, when hitlag ends
+            hit(0.0, -0.5, 0.866),
+            hit(0.0, 0.0, 0.0),
+        ];
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].di, HashMap::from([("survival".to_string(), 1)]));
+        assert_eq!(player_moves[0].sdi_inputs, 2);
+        
+        // The other ways to hold the stick against the same launch
+        let stick = |x: f32, y: f32| transpose::Position { x, y };
+        assert_eq!(classify_di(knockback_x, knockback_y, stick(0.5, -0.866)), Some("out"));
+        assert_eq!(classify_di(knockback_x, knockback_y, stick(0.866, 0.5)), Some("none"));
+        assert_eq!(classify_di(knockback_x, knockback_y, stick(0.0, 0.0)), Some("none"));
+        // A steep launch held the same way rotates towards vertical
+        let (steep_x, steep_y) = (60f32.to_radians().cos(), 60f32.to_radians().sin());
+        assert_eq!(classify_di(steep_x, steep_y, stick(-0.866, 0.5)), Some("in"));
+        assert_eq!(classify_di(0.0, 0.0, stick(1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_grab_into_throw() {
+        use action_states::*;
+        
+        let states = [WAIT, CATCH, CATCH_PULL, CATCH_WAIT, CATCH_ATTACK, CATCH_WAIT, THROW_LW, THROW_LW, WAIT, CATCH, WAIT];
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(states.iter().map(|&state| synthetic_frame(state)), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        // The second grab whiffed
+        assert_eq!(player_moves[0].moves.get("grab"), Some(&2));
+        assert_eq!(player_moves[0].moves.get("dthrow"), Some(&1));
+        assert_eq!(player_moves[0].grab_success_rate, 0.5);
+    }
+
+    #[test]
+    fn test_dash_dance_and_pivot() {
+        use action_states::*;
+        
+        let dash = |stick_x| {
+            let mut frame = synthetic_frame(DASH);
+            frame.ports[0].leader.pre.joystick.x = stick_x;
+            frame
+        };
+        let mut frames = vec![synthetic_frame(WAIT)];
+        // Right, left, right, left: three reversals
+        for stick_x in [1.0, -1.0, 1.0, -1.0] {
+            frames.extend((0..4).map(|_| dash(stick_x)));
+        }
+        // A reversal after running isn't a dash-dance
+        frames.extend((0..10).map(|_| synthetic_frame(RUN)));
+        frames.extend((0..4).map(|_| dash(1.0)));
+        // Dash, turn, stand still
+        frames.extend([synthetic_frame(TURN), synthetic_frame(TURN), synthetic_frame(WAIT)]);
+        // A slow turnaround from standing isn't a pivot
+        frames.extend([synthetic_frame(TURN), synthetic_frame(WAIT)]);
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        assert_eq!(player_moves[0].moves.get("dash_dance"), Some(&3));
+        assert_eq!(player_moves[0].moves.get("pivot"), Some(&1));
+    }
+
+    #[test]
+    fn test_rar_and_turnaround_b() {
+        use action_states::*;
+        
+        let facing = |state, direction| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.pre.joystick.x = direction;
+            frame.ports[0].leader.post.direction = direction;
+            frame
+        };
+        let mut frames = vec![facing(WAIT, 1.0)];
+        // Dash right, turn around in jumpsquat, back-air to the right
+        frames.extend((0..4).map(|_| facing(DASH, 1.0)));
+        frames.extend((0..3).map(|_| facing(KNEE_BEND, -1.0)));
+        frames.extend((0..3).map(|_| facing(JUMP_F, -1.0)));
+        frames.extend((0..5).map(|_| facing(ATTACK_AIR_B, -1.0)));
+        frames.extend((0..4).map(|_| facing(LANDING_AIR_B, -1.0)));
+        // A dash-jump that keeps facing forward, then a back-air, isn't a RAR
+        frames.extend((0..4).map(|_| facing(DASH, 1.0)));
+        frames.extend((0..3).map(|_| facing(KNEE_BEND, 1.0)));
+        frames.extend((0..3).map(|_| facing(JUMP_F, 1.0)));
+        frames.extend((0..5).map(|_| facing(ATTACK_AIR_B, 1.0)));
+        frames.extend((0..4).map(|_| facing(LANDING_AIR_B, 1.0)));
+        // Laser forwards, then turned around
+        frames.extend((0..3).map(|_| facing(0x155, 1.0)));
+        frames.extend((0..3).map(|_| facing(WAIT, 1.0)));
+        frames.extend((0..3).map(|_| facing(0x155, -1.0)));
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        assert_eq!(player_moves[0].moves.get("bair"), Some(&2));
+        assert_eq!(player_moves[0].moves.get("rar"), Some(&1));
+        assert_eq!(player_moves[0].moves.get("laser"), Some(&2));
+        assert_eq!(player_moves[0].moves.get("turnaround_b"), Some(&1));
+    }
+
+    #[test]
+    fn test_platform_drops_and_wavelands() {
+        use action_states::*;
+        
+        let at = |state: u16, x: f32, y: f32| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.position = transpose::Position { x, y };
+            frame
+        };
+        let frames = || {
+            // Drop through Battlefield's left platform into a nair
+            let mut frames = vec![at(WAIT, -38.0, 27.2), at(PASS, -38.0, 27.0), at(FALL, -38.0, 25.0), at(FALL, -38.0, 22.0), at(ATTACK_AIR_N, -38.0, 18.0)];
+            // Drop through the top platform, but the fair comes too late
+            frames.push(at(WAIT, 0.0, 54.4));
+            frames.push(at(PASS, 0.0, 54.2));
+            frames.extend((0..15).map(|i| at(FALL, 0.0, 50.0 - i as f32)));
+            frames.push(at(ATTACK_AIR_F, 0.0, 30.0));
+            // Waveland onto the right platform, then a wavedash on the main stage
+            frames.extend([at(JUMP_F, 30.0, 10.0), at(ESCAPE_AIR, 35.0, 28.0), at(LANDING_FALL_SPECIAL, 38.0, 27.2)]);
+            frames.extend([at(WAIT, 10.0, 0.0), at(KNEE_BEND, 10.0, 0.0), at(ESCAPE_AIR, 10.0, 2.0), at(LANDING_FALL_SPECIAL, 12.0, 0.0)]);
+            frames
+        };
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames().into_iter(), &mut player_moves, TEST_VERSION, stage_bounds(31), IcsMode::Merge, OPENING_RESET_FRAMES);
+        let moves = &player_moves[0].moves;
+        assert_eq!(moves.get("platform_drop"), Some(&2));
+        assert_eq!(moves.get("platform_drop_aerial"), Some(&1));
+        assert_eq!(moves.get("platform_waveland"), Some(&1));
+        
+        // Platforms come from the stage, so nothing is counted without one
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames().into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("platform_drop"), None);
+    }
+
+    #[test]
+    fn test_multishine_and_shine_grab() {
+        use action_states::*;
+        
+        let shine = || (0..4).map(|_| synthetic_frame(0x168));
+        let jumpsquat = || (0..3).map(|_| synthetic_frame(KNEE_BEND));
+        let mut frames = vec![synthetic_frame(WAIT)];
+        // Shine, jump, shine, jump, shine: two multishines
+        frames.extend(shine());
+        frames.extend(jumpsquat());
+        frames.push(synthetic_frame(JUMP_F));
+        frames.extend(shine());
+        frames.extend(jumpsquat());
+        frames.extend(shine());
+        // Shine, jump-cancel, grab
+        frames.extend(jumpsquat());
+        frames.extend((0..5).map(|_| synthetic_frame(CATCH)));
+        frames.extend((0..5).map(|_| synthetic_frame(WAIT)));
+        // A shine long after a jump isn't a multishine
+        frames.extend(shine());
+        frames.extend(jumpsquat());
+        frames.extend((0..20).map(|_| synthetic_frame(FALL)));
+        frames.extend(shine());
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("shine"), Some(&5));
+        assert_eq!(player_moves[0].moves.get("multishine"), Some(&2));
+        assert_eq!(player_moves[0].moves.get("shine_grab"), Some(&1));
+    }
+
+    #[test]
+    fn test_dacus() {
+        use action_states::*;
+        
+        let state = |state, count| (0..count).map(move |_| synthetic_frame(state));
+        let mut frames: Vec<_> = state(DASH, 3).collect();
+        // Dash attack cancelled into up-smash two frames in: a DACUS
+        frames.extend(state(ATTACK_DASH, 2));
+        frames.extend(state(ATTACK_HI4, 10));
+        frames.extend(state(WAIT, 5));
+        // A plain dash attack, then one that only turns into an up-smash once
+        // it has run its course
+        frames.extend(state(ATTACK_DASH, 20));
+        frames.extend(state(WAIT, 5));
+        frames.extend(state(ATTACK_DASH, 20));
+        frames.extend(state(ATTACK_HI4, 10));
+        
+        let mut player_moves = empty_player_moves("Fox");
+        let events = analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("dacus"), Some(&1));
+        assert_eq!(player_moves[0].moves.get("dash_attack"), Some(&2));
+        assert_eq!(player_moves[0].moves.get("usmash"), Some(&2));
+        // The cancelled dash attack is neither a hit nor a whiff
+        assert_eq!(player_moves[0].move_outcomes["dash_attack"].whiffs, 2);
+        let timeline: Vec<&str> = events.timeline.iter().map(|event| event.move_name.as_str()).collect();
+        assert_eq!(timeline, vec!["dacus", "usmash", "dash_attack", "dash_attack", "usmash"]);
+    }
+
+    #[test]
+    fn test_shield_options() {
+        use action_states::*;
+        
+        let states = [WAIT, GUARD_ON, GUARD, GUARD, GUARD_SET_OFF, GUARD, ESCAPE_B, ESCAPE_B, WAIT, GUARD_ON, GUARD, CATCH, WAIT, CATCH];
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(states.iter().map(|&state| synthetic_frame(state)), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        let moves = &player_moves[0].moves;
+        assert_eq!(player_moves[0].shield_frames, 7);
+        assert_eq!(moves.get("roll_back"), Some(&1));
+        assert_eq!(moves.get("roll_forward"), None);
+        // Only the first grab came out of shield
+        assert_eq!(moves.get("oos_grab"), Some(&1));
+        assert_eq!(moves.get("grab"), Some(&2));
+    }
+
+    #[test]
+    fn test_invincible_frames() {
+        use action_states::*;
+        
+        // Respawn invulnerability, then vulnerable, then a roll's intangibility
+        let frames = [(WAIT, 1), (WAIT, 1), (WAIT, 0), (ESCAPE_F, 2), (ESCAPE_F, 2), (ESCAPE_F, 2), (WAIT, 0)].map(|(state, hurtbox_state)| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.hurtbox_state = Some(hurtbox_state);
+            frame
+        });
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].invincible_frames, 5);
+        
+        // Replays without hurtbox state don't count any
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames((0..3).map(|_| synthetic_frame(WAIT)), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].invincible_frames, 0);
+    }
+
+    #[test]
+    fn test_actionable_ratio() {
+        use action_states::*;
+        
+        // Six frames standing, four in hitstun
+        let frames = [WAIT, WAIT, WAIT, DAMAGE_HI_1, DAMAGE_HI_1, DAMAGE_HI_1, DAMAGE_HI_1, WAIT, WAIT, WAIT].map(synthetic_frame);
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].actionable_frames, 6);
+        assert!((player_moves[0].actionable_ratio - 0.6).abs() < 0.001);
+        
+        // Merging weighs each game by its length
+        let mut merged = player_moves.remove(0);
+        merged.merge(PlayerMoveData { frames_played: 10, actionable_frames: 10, ..Default::default() });
+        assert!((merged.actionable_ratio - 0.8).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_three_hit_combo() {
+        use action_states::*;
+        
+        let hit = |attacker_state, victim_state, percent| {
+            let mut frame = hit_frame(victim_state, percent);
+            frame.ports[0].leader.pre.state = attacker_state;
+            frame
+        };
+        let combo = || {
+            let mut frames = vec![hit(WAIT, WAIT, 0.0), hit(ATTACK_LW3, DAMAGE_HI_1, 12.0)];
+            frames.extend((0..10).map(|_| hit(WAIT, DAMAGE_FALL, 12.0)));
+            frames.push(hit(ATTACK_AIR_HI, DAMAGE_HI_1, 25.0));
+            frames.push(hit(ATTACK_AIR_HI, DAMAGE_FLY_ROLL, 38.0));
+            frames.extend((0..20).map(|_| hit(WAIT, WAIT, 38.0)));
+            // A lone hit long after the combo ended
+            frames.push(hit(ATTACK_AIR_N, DAMAGE_HI_1, 50.0));
+            frames
+        };
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(combo().into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, 15);
+        assert_eq!(player_moves[0].combos, vec![Combo {
+            victim: 1,
+            start_frame: 1,
+            starting_move: "dtilt".to_string(),
+            hits: 3,
+            damage: 38.0,
+            killed: false,
+        }]);
+        
+        // A longer reset window joins the lone hit onto the combo
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(combo().into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, 30);
+        assert_eq!(player_moves[0].combos[0].hits, 4);
+    }
+
+    #[test]
+    fn test_combos_dropped_and_escaped() {
+        use action_states::*;
+        
+        let hit = |attacker_state, victim_state, percent| {
+            let mut frame = hit_frame(victim_state, percent);
+            frame.ports[0].leader.pre.state = attacker_state;
+            frame.ports[1].leader.post.stocks = 4;
+            frame
+        };
+        // A two-hit combo, then the victim is actionable long enough to get out
+        // before the follow-up; a second combo ends in a kill
+        let mut frames = vec![hit(WAIT, WAIT, 0.0), hit(ATTACK_LW3, DAMAGE_HI_1, 12.0), hit(ATTACK_AIR_HI, DAMAGE_HI_1, 25.0)];
+        frames.extend((0..20).map(|_| hit(WAIT, WAIT, 25.0)));
+        frames.push(hit(ATTACK_AIR_F, DAMAGE_HI_1, 35.0));
+        frames.push(hit(ATTACK_AIR_F, DAMAGE_FLY_ROLL, 50.0));
+        let mut kill = hit(WAIT, DAMAGE_FLY_ROLL, 0.0);
+        kill.ports[1].leader.post.stocks = 3;
+        frames.push(kill);
+        
+        let mut player_moves = empty_player_moves("Fox");
+        player_moves.push(PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() });
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, 15);
+        assert_eq!(player_moves[0].combos.iter().map(|combo| combo.killed).collect::<Vec<_>>(), vec![false, true]);
+        assert_eq!(player_moves[0].combos_dropped, 1);
+        assert_eq!(player_moves[1].combos_escaped, 1);
+        assert_eq!(player_moves[1].combos_dropped, 0);
+    }
+
+    #[test]
+    fn test_stock_events() {
+        use peppi::game::Port;
+        
+        let stocks = [4, 4, 3, 3, 3];
+        let frames = stocks.iter().map(|&p2_stocks| final_frame(&[(Port::P1, 4, 0.0), (Port::P2, p2_stocks, 0.0)]));
+        let mut player_moves = empty_player_moves("Fox");
+        player_moves.push(PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() });
+        let events = analyze_frames(frames, &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(events.stock_events, vec![StockEvent { frame: 2, port: 1, remaining_stocks: 3 }]);
+        
+        // The stock lost and the one still in play
+        assert_eq!(player_moves[0].stocks_used, 1);
+        assert_eq!(player_moves[1].stocks_used, 2);
+    }
+
+    #[test]
+    fn test_last_stock_moves() {
+        use action_states::*;
+        
+        // A nair on the second-to-last stock, then a nair and a fair on the last
+        let frames = [(2, ATTACK_AIR_N), (2, WAIT), (1, WAIT), (1, ATTACK_AIR_N), (1, WAIT), (1, ATTACK_AIR_F)].map(|(stocks, state)| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.stocks = stocks;
+            frame
+        });
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("nair"), Some(&2));
+        assert_eq!(player_moves[0].last_stock_moves.get("nair"), Some(&1));
+        assert_eq!(player_moves[0].last_stock_moves.get("fair"), Some(&1));
+        assert_eq!(player_moves[0].last_stock_moves.len(), 2);
+        
+        // Techniques are split the same way, and a DACUS is recounted in both:
+        // a DACUS, then a wavedash, all on the last stock
+        let states = [DASH, ATTACK_DASH, ATTACK_DASH, ATTACK_HI4, ATTACK_HI4, WAIT, KNEE_BEND, ESCAPE_AIR, LANDING_FALL_SPECIAL, LANDING_FALL_SPECIAL, WAIT];
+        let frames = states.map(|state| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.stocks = 1;
+            frame.ports[0].leader.post.airborne = Some(u8::from(matches!(state, ESCAPE_AIR)));
+            frame
+        });
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].last_stock_moves.get("dacus"), Some(&1));
+        assert_eq!(player_moves[0].last_stock_moves.get("wavedash"), Some(&1));
+        assert_eq!(player_moves[0].last_stock_moves.get("dash_attack"), None);
+        assert_eq!(player_moves[0].last_stock_moves, player_moves[0].moves);
+    }
+
+    #[test]
+    fn test_first_and_last_hit() {
+        use peppi::game::Port;
+        
+        let frame = |p1_percent: f32, p2_percent: f32| {
+            let data = |percent: f32, last_hit_by: u8| transpose::Data {
+                pre: transpose::Pre::default(),
+                post: transpose::Post { percent, last_hit_by, stocks: 4, ..Default::default() },
+            };
+            transpose::Frame {
+                ports: vec![
+                    transpose::PortData { port: Port::P1, leader: data(p1_percent, 1), follower: None },
+                    transpose::PortData { port: Port::P2, leader: data(p2_percent, 0), follower: None },
+                ],
+                ..Default::default()
+            }
+        };
+        let players = || vec![
+            PlayerMoveData { port: 0, character: "Fox".to_string(), ..Default::default() },
+            PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() },
+        ];
+        
+        // Port 2 lands the first hit, then port 1 the next two
+        let frames = [(0.0, 0.0), (12.0, 0.0), (12.0, 8.0), (12.0, 8.0), (12.0, 21.0)].map(|(p1, p2)| frame(p1, p2));
+        let events = analyze_frames(frames.into_iter(), &mut players(), TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(events.first_hit_port, Some(1));
+        assert_eq!(events.last_hit_port, Some(0));
+        
+        // Nobody is hit
+        let frames = (0..5).map(|_| frame(0.0, 0.0));
+        let events = analyze_frames(frames, &mut players(), TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(events.first_hit_port, None);
+        assert_eq!(events.last_hit_port, None);
+    }
+
+    #[test]
+    fn test_match_type() {
+        assert_eq!(match_type(Some("mode.ranked-2025-06-14T20:15:00.00-0"), true).as_deref(), Some("ranked"));
+        assert_eq!(match_type(Some("mode.unranked-2025-06-14T20:15:00.00-0"), true).as_deref(), Some("unranked"));
+        assert_eq!(match_type(Some("mode.direct-2025-06-14T20:15:00.00-0"), true).as_deref(), Some("direct"));
+        assert_eq!(match_type(Some("mode.teams-2025-06-14T20:15:00.00-0"), true), None);
+        assert_eq!(match_type(None, true), None);
+        assert_eq!(match_type(None, false).as_deref(), Some("offline"));
+    }
+
+    #[tokio::test]
+    async fn test_zelda_sheik_transformations() {
+        // Port 1 starts as Zelda, transforms to Sheik after GO! and back again
+        let go = slippi_parser_service::COUNTDOWN_FRAMES as usize;
+        let mut game = test_game(go + 60, |frames| {
+            let character = &mut frames.ports[0].leader.post.character;
+            for frame_idx in 0..go + 60 {
+                let sheik = (go + 10..go + 40).contains(&frame_idx);
+                character.set(frame_idx, Some(if sheik { 7 } else { 19 }));
+            }
+        });
+        game.start.players[0].character = 18;
+        let mut bytes = Vec::new();
+        peppi::io::slippi::write(&mut bytes, &game).unwrap();
+        
+        let config = AnalysisConfig::builder().extract_moves(true).build();
+        let game_data = parse_slippi_file(Cursor::new(bytes), None, &config).await.unwrap();
+        assert_eq!(game_data.players[0].character, "Zelda");
+        assert_eq!(game_data.players[0].characters, vec!["Zelda", "Sheik"]);
+        assert_eq!(game_data.moves.as_ref().unwrap()[0].transformations, 2);
+        
+        // Players who don't transform only have `character`
+        assert!(game_data.players[1].characters.is_empty());
+        assert_eq!(game_data.moves.as_ref().unwrap()[1].transformations, 0);
+        assert!(serde_json::to_value(&game_data.players[1]).unwrap().get("characters").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_single_game_csv() {
+        let go = slippi_parser_service::COUNTDOWN_FRAMES as usize;
+        let game = test_game(go + 60, |frames| {
+            frames.ports[0].leader.pre.state.set(go + 1, Some(action_states::ATTACK_AIR_F));
+        });
+        let mut bytes = Vec::new();
+        peppi::io::slippi::write(&mut bytes, &game).unwrap();
+        
+        let config = AnalysisConfig::builder().extract_moves(true).build();
+        let game_data = parse_slippi_file(Cursor::new(bytes), None, &config).await.unwrap();
+        let csv = generate_csv_output(game_data.moves.as_deref().unwrap(), CsvLayout::Long);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "port,character,move,count");
+        assert!(lines.contains(&"0,Fox,fair,1"));
+    }
+
+    #[test]
+    fn test_move_timeline() {
+        use action_states::*;
+        use peppi::game::Port;
+        
+        let frame = |p1_state: u16, p2_state: u16| {
+            let data = |state: u16| transpose::Data {
+                pre: transpose::Pre { state, ..Default::default() },
+                post: transpose::Post { state, stocks: 4, ..Default::default() },
+            };
+            transpose::Frame {
+                ports: vec![
+                    transpose::PortData { port: Port::P1, leader: data(p1_state), follower: None },
+                    transpose::PortData { port: Port::P2, leader: data(p2_state), follower: None },
+                ],
+                ..Default::default()
+            }
+        };
+        // Port 2 fairs on frame 1, port 1 nairs on frame 3 and holds it
+        let frames = [(WAIT, WAIT), (WAIT, ATTACK_AIR_F), (WAIT, WAIT), (ATTACK_AIR_N, WAIT), (ATTACK_AIR_N, WAIT)].map(|(p1, p2)| frame(p1, p2));
+        let mut player_moves = vec![
+            PlayerMoveData { port: 0, character: "Fox".to_string(), ..Default::default() },
+            PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() },
+        ];
+        let events = analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(events.timeline, vec![
+            MoveEvent { frame: 1, port: 1, move_name: "fair".to_string() },
+            MoveEvent { frame: 3, port: 0, move_name: "nair".to_string() },
+        ]);
+        assert_eq!(serde_json::to_value(&events.timeline[0]).unwrap(), serde_json::json!({"frame": 1, "port": 1, "move": "fair"}));
+    }
+
+    #[test]
+    fn test_timeline_includes_techniques() {
+        use action_states::*;
+        
+        // A wavedash out of standing: the jump, air dodge and landing are one technique
+        let frame = |state: u16, x: f32| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.pre.joystick = transpose::Position { x: 1.0, y: -0.3 };
+            frame.ports[0].leader.post.position = transpose::Position { x, y: 0.0 };
+            frame.ports[0].leader.post.airborne = Some(if state == LANDING_FALL_SPECIAL { 0 } else { 1 });
+            frame
+        };
+        let frames = [frame(WAIT, 0.0), frame(KNEE_BEND, 0.0), frame(ESCAPE_AIR, 0.0), frame(LANDING_FALL_SPECIAL, 6.0), frame(WAIT, 12.0)];
+        let mut player_moves = empty_player_moves("Fox");
+        let events = analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        let timeline: Vec<(u32, &str)> = events.timeline.iter().map(|event| (event.frame, event.move_name.as_str())).collect();
+        assert_eq!(timeline, vec![(3, "wavedash"), (4, "perfect_wavedash")]);
+        // The timeline lists exactly what was counted
+        let mut tallies: HashMap<String, u32> = HashMap::new();
+        for (_, move_name) in &timeline {
+            *tallies.entry(move_name.to_string()).or_insert(0) += 1;
+        }
+        assert_eq!(tallies, player_moves[0].moves);
+    }
+
+    #[test]
+    fn test_iter_moves() {
+        // Fox fairs and Falco nairs after GO!, and the moves survive a round trip through .slp
+        let go = slippi_parser_service::COUNTDOWN_FRAMES as usize;
+        let game = test_game(go + 60, |frames| {
+            frames.ports[0].leader.pre.state.set(go + 1, Some(action_states::ATTACK_AIR_F));
+            frames.ports[1].leader.pre.state.set(go + 5, Some(action_states::ATTACK_AIR_N));
+        });
+        let mut bytes = Vec::new();
+        peppi::io::slippi::write(&mut bytes, &game).unwrap();
+        let game = read(&mut Cursor::new(&bytes), None).unwrap();
+        
+        let moves: Vec<MoveEvent> = iter_moves(&game, game.start.slippi.version).collect();
+        assert_eq!(moves.iter().map(|e| (e.port, e.move_name.as_str())).collect::<Vec<_>>(), vec![(0, "fair"), (1, "nair")]);
+        assert!(moves[0].frame < moves[1].frame);
+        // Same events --timeline prints
+        assert_eq!(moves, move_timeline(Cursor::new(bytes), &AnalysisConfig::default()).unwrap());
+    }
+
+    #[test]
+    fn test_non_contiguous_ports() {
+        use peppi::game::Port;
+        
+        // Players on ports 1 and 3: Fox fairs, Marth nairs
+        let mut frames = mutable_frames_for_ports(TEST_VERSION, 4, &[Port::P1, Port::P3]);
+        frames.ports[0].leader.pre.state.set(1, Some(action_states::ATTACK_AIR_F));
+        frames.ports[1].leader.pre.state.set(2, Some(action_states::ATTACK_AIR_N));
+        let frames: Frame = frames.into();
+        let players = [test_player(Port::P1, 2, None), test_player(Port::P3, 9, None)];
+        
+        let (moves, events) = extract_moves_from_frames(&frames, &players, TEST_VERSION, 31, IcsMode::Merge, FrameRange::default(), OPENING_RESET_FRAMES);
+        assert_eq!(moves.iter().map(|p| p.port).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(moves[0].moves.get("fair"), Some(&1));
+        assert_eq!(moves[1].moves.get("nair"), Some(&1));
+        assert_eq!(events.timeline.iter().map(|e| (e.port, e.move_name.as_str())).collect::<Vec<_>>(), vec![(0, "fair"), (2, "nair")]);
+        
+        // Attribution follows the port, not where the player sits in the list
+        let mut player_moves = vec![
+            PlayerMoveData { port: 2, character: "Marth".to_string(), ..Default::default() },
+            PlayerMoveData { port: 0, character: "Fox".to_string(), ..Default::default() },
+        ];
+        analyze_frames((0..4).map(|frame_idx| frames.transpose_one(frame_idx, TEST_VERSION)), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("nair"), Some(&1));
+        assert_eq!(player_moves[1].moves.get("fair"), Some(&1));
+    }
+
+    #[test]
+    fn test_frame_range() {
+        assert_eq!(FrameRange::default().clamp(100), 0..100);
+        assert_eq!(FrameRange { start: Some(10), end: Some(20) }.clamp(100), 10..20);
+        assert_eq!(FrameRange { start: Some(-30), end: None }.clamp(100), 70..100);
+        assert_eq!(FrameRange { start: Some(-300), end: Some(500) }.clamp(100), 0..100);
+        assert_eq!(FrameRange { start: Some(80), end: Some(20) }.clamp(100), 80..80);
+        
+        // A fair at frame 1 and a nair at frame 4; only the window's moves count
+        let mut frames = mutable_frames(TEST_VERSION, 6);
+        for (frame_idx, state) in [(1, action_states::ATTACK_AIR_F), (2, action_states::WAIT), (4, action_states::ATTACK_AIR_N)] {
+            frames.ports[0].leader.pre.state.set(frame_idx, Some(state));
+        }
+        let frames: Frame = frames.into();
+        let players = [test_player(peppi::game::Port::P1, 2, None)];
+        
+        let (moves, _) = extract_moves_from_frames(&frames, &players, TEST_VERSION, 31, IcsMode::Merge, FrameRange::default(), OPENING_RESET_FRAMES);
+        assert_eq!(moves[0].moves.get("fair"), Some(&1));
+        assert_eq!(moves[0].moves.get("nair"), Some(&1));
+        
+        let last_frames = FrameRange { start: Some(-3), end: None };
+        let (moves, _) = extract_moves_from_frames(&frames, &players, TEST_VERSION, 31, IcsMode::Merge, last_frames, OPENING_RESET_FRAMES);
+        assert_eq!(moves[0].moves.get("fair"), None);
+        assert_eq!(moves[0].moves.get("nair"), Some(&1));
+        assert_eq!(moves[0].frames_played, 3);
+    }
+
+    #[test]
+    fn test_frames_transposed_with_replay_version() {
+        // A 3.16 replay carries frame-end and item data, plus 3.16 post-frame fields
+        let frames = frames_with_version(Version(3, 16, 0), 2);
+        let transposed: Vec<_> = transpose_frames(&frames, Version(3, 16, 0), 0..2).collect();
+        assert_eq!(transposed.len(), 2);
+        assert!(transposed[0].end.is_some());
+        assert_eq!(transposed[0].items, Some(vec![]));
+        assert!(transposed[0].ports[0].leader.post.instance_id.is_some());
+        
+        // A pre-3.0 replay has no frame-end data; decoding it as 3.0 would
+        // panic, but with its own version it transposes cleanly
+        let frames = frames_with_version(Version(2, 0, 0), 2);
+        let transposed: Vec<_> = transpose_frames(&frames, Version(2, 0, 0), 0..2).collect();
+        assert_eq!(transposed.len(), 2);
+        assert!(transposed[0].start.is_none());
+        assert!(transposed[0].end.is_none());
+    }
+
+    #[test]
+    fn test_move_counted_once_per_transition() {
+        let mut player_moves = empty_player_moves("Fox");
+        let frames = vec![
+            synthetic_frame(action_states::ATTACK_AIR_F),
+            synthetic_frame(action_states::ATTACK_AIR_F),
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("fair"), Some(&1));
+        
+        // Leaving the state and re-entering it is a second fair
+        let mut player_moves = empty_player_moves("Fox");
+        let frames = vec![
+            synthetic_frame(action_states::ATTACK_AIR_F),
+            synthetic_frame(action_states::ATTACK_AIR_F),
+            synthetic_frame(action_states::JUMP_AERIAL_F),
+            synthetic_frame(action_states::ATTACK_AIR_F),
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("fair"), Some(&2));
+        assert_eq!(player_moves[0].moves.get("double_jump"), Some(&1));
+    }
+
+    #[test]
+    fn test_tech_situations() {
+        let mut player_moves = empty_player_moves("Fox");
+        let mut tech_roll = synthetic_frame(action_states::PASSIVE_STAND_F);
+        tech_roll.ports[0].leader.post.direction = -1.0;
+        let frames = vec![
+            synthetic_frame(action_states::DAMAGE_FALL),
+            tech_roll,
+            synthetic_frame(action_states::DAMAGE_FALL),
+            synthetic_frame(action_states::PASSIVE),
+            synthetic_frame(action_states::DAMAGE_FALL),
+            synthetic_frame(action_states::DOWN_BOUND_U),
+            synthetic_frame(action_states::DOWN_WAIT_U),
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        let moves = &player_moves[0].moves;
+        assert_eq!(moves.get("tech_roll"), Some(&1));
+        assert_eq!(moves.get("tech_roll_left"), Some(&1));
+        assert_eq!(moves.get("tech_in_place"), Some(&1));
+        assert_eq!(moves.get("missed_tech"), Some(&1));
+    }
+
+    #[test]
+    fn test_ledgedash_galint() {
+        let frame = |state: u16, airborne: u8, hurtbox_state: u8| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.airborne = Some(airborne);
+            frame.ports[0].leader.post.hurtbox_state = Some(hurtbox_state);
+            frame
+        };
+        
+        // Drop from ledge, double jump, airdodge onto stage: 3 frames of landing
+        // lag, then 5 more intangible frames once actionable
+        let mut frames = vec![
+            frame(action_states::CLIFF_WAIT, 0, 1),
+            frame(action_states::FALL, 1, 1),
+            frame(action_states::JUMP_AERIAL_F, 1, 1),
+            frame(action_states::ESCAPE_AIR, 1, 1),
+            frame(action_states::ESCAPE_AIR, 1, 1),
+        ];
+        frames.extend((0..3).map(|_| frame(action_states::LANDING_FALL_SPECIAL, 0, 1)));
+        frames.extend((0..5).map(|_| frame(action_states::WAIT, 0, 1)));
+        frames.push(frame(action_states::WAIT, 0, 0));
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("ledgedash"), Some(&1));
+        assert_eq!(player_moves[0].galint_samples, 1);
+        assert_eq!(player_moves[0].average_galint, 5.0);
+    }
+
+    #[test]
+    fn test_ledge_grabs_and_time() {
+        use action_states::*;
+        
+        let mut frames = vec![synthetic_frame(FALL)];
+        // Grab the ledge for 10 frames, then drop off
+        frames.extend((0..2).map(|_| synthetic_frame(CLIFF_CATCH)));
+        frames.extend((0..8).map(|_| synthetic_frame(CLIFF_WAIT)));
+        frames.extend((0..5).map(|_| synthetic_frame(FALL)));
+        // Grab it again and get hit off after 6 frames
+        frames.extend((0..2).map(|_| synthetic_frame(CLIFF_CATCH)));
+        frames.extend((0..4).map(|_| synthetic_frame(CLIFF_WAIT)));
+        frames.extend((0..5).map(|_| synthetic_frame(DAMAGE_HI_1)));
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].ledge_grabs, 2);
+        assert_eq!(player_moves[0].ledge_releases, 1);
+        assert_eq!(player_moves[0].ledge_knockoffs, 1);
+        assert_eq!(player_moves[0].average_ledge_time, 10.0);
+    }
+
+    #[test]
+    fn test_wavedash_angle_and_distance() {
+        let frame = |state: u16, x: f32, stick_degrees: f32| {
+            let mut frame = synthetic_frame(state);
+            let radians = (-stick_degrees).to_radians();
+            frame.ports[0].leader.pre.joystick = transpose::Position { x: radians.cos(), y: radians.sin() };
+            frame.ports[0].leader.post.position = transpose::Position { x, y: 0.0 };
+            frame.ports[0].leader.post.airborne = Some(if state == action_states::LANDING_FALL_SPECIAL { 0 } else { 1 });
+            frame
+        };
+        let wavedash = |start: u16, stick_degrees: f32| vec![
+            frame(start, 0.0, 0.0),
+            frame(action_states::KNEE_BEND, 0.0, 0.0),
+            frame(action_states::ESCAPE_AIR, 0.0, stick_degrees),
+            frame(action_states::LANDING_FALL_SPECIAL, 6.0, 0.0),
+            frame(action_states::LANDING_FALL_SPECIAL, 12.0, 0.0),
+            frame(action_states::WAIT, 15.0, 0.0),
+        ];
+        
+        let mut frames = wavedash(action_states::WAIT, 18.0);
+        frames.extend(wavedash(action_states::WAIT, 45.0));
+        frames.extend(wavedash(action_states::GUARD, 18.0));
+        
+        let mut player_moves = empty_player_moves("Fox");
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        let player = &player_moves[0];
+        assert_eq!(player.moves.get("wavedash"), Some(&3));
+        assert_eq!(player.moves.get("perfect_wavedash"), Some(&1));
+        assert_eq!(player.moves.get("shallow_wavedash"), Some(&1));
+        assert_eq!(player.moves.get("wavedash_oos"), Some(&1));
+        assert_eq!(player.wavedash_samples, 3);
+        assert!((player.average_wavedash_angle - 27.0).abs() < 1e-3);
+        assert_eq!(player.average_wavedash_distance, 15.0);
+    }
+
+    #[test]
+    fn test_l_cancel_success_rate() {
+        let landing = |state: u16, l_cancel: u8| {
+            let mut frame = synthetic_frame(state);
+            frame.ports[0].leader.post.l_cancel = Some(l_cancel);
+            frame
+        };
+        
+        // Three aerial landings, two of them cancelled
+        let mut player_moves = empty_player_moves("Fox");
+        let frames = vec![
+            synthetic_frame(action_states::ATTACK_AIR_N),
+            landing(action_states::LANDING_AIR_N, 1),
+            landing(action_states::LANDING_AIR_N, 1),
+            synthetic_frame(action_states::ATTACK_AIR_F),
+            landing(action_states::LANDING_AIR_F, 2),
+            synthetic_frame(action_states::ATTACK_AIR_LW),
+            landing(action_states::LANDING_AIR_LW, 1),
+        ];
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].aerial_landings, 3);
+        assert_eq!(player_moves[0].moves.get("l_cancel"), Some(&2));
+        assert!((player_moves[0].l_cancel_success_rate - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_apm_from_input_changes() {
+        // Pressing A every other frame for one minute is 1800 presses
+        let mut player_moves = empty_player_moves("Fox");
+        let frames = (0..3600).map(|frame_idx| {
+            let mut frame = synthetic_frame(action_states::JUMP_F);
+            frame.ports[0].leader.pre.buttons_physical = if frame_idx % 2 == 0 { 0x0100 } else { 0 };
+            frame
+        });
+        analyze_frames(frames, &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].actions, 1800);
+        assert_eq!(player_moves[0].apm, 1800.0);
+        
+        // Holding a direction is one action; releasing to neutral is none
+        let mut player_moves = empty_player_moves("Fox");
+        let frames = [1.0, 1.0, 0.0, -1.0].iter().map(|&x| {
+            let mut frame = synthetic_frame(action_states::JUMP_F);
+            frame.ports[0].leader.pre.joystick = transpose::Position { x, y: 0.0 };
+            frame
+        });
+        analyze_frames(frames, &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].actions, 2);
+    }
+
+    // A P1/P2 frame where P2 is in the given state at the given percent, last hit by P1
+    fn hit_frame(p2_state: u16, p2_percent: f32) -> transpose::Frame {
+        let mut frame = synthetic_frame(action_states::JUMP_F);
+        frame.ports.push(transpose::PortData {
+            port: peppi::game::Port::P2,
+            leader: transpose::Data {
+                pre: transpose::Pre { state: p2_state, ..Default::default() },
+                post: transpose::Post { state: p2_state, percent: p2_percent, last_hit_by: 0, ..Default::default() },
+            },
+            follower: None,
+        });
+        frame
+    }
+
+    #[test]
+    fn test_ice_climbers_follower() {
+        let ics_frames = || (0..2).map(|_| {
+            let mut frame = synthetic_frame(action_states::ATTACK_AIR_F);
+            frame.ports[0].follower = Some(transpose::Data {
+                pre: transpose::Pre { state: action_states::ATTACK_AIR_B, ..Default::default() },
+                post: transpose::Post { state: action_states::ATTACK_AIR_B, ..Default::default() },
+            });
+            frame
+        });
+        
+        let mut player_moves = empty_player_moves("Ice Climbers");
+        analyze_frames(ics_frames(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("fair"), Some(&1));
+        assert_eq!(player_moves[0].moves.get("bair"), Some(&1));
+        assert_eq!(player_moves[0].frames_played, 2);
+        
+        let mut player_moves = empty_player_moves("Ice Climbers");
+        analyze_frames(ics_frames(), &mut player_moves, TEST_VERSION, None, IcsMode::Split, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].moves.get("fair"), Some(&1));
+        assert_eq!(player_moves[0].moves.get("bair"), None);
+        assert_eq!(player_moves[0].moves.get("bair_nana"), Some(&1));
+    }
+
+    #[test]
+    fn test_damage_dealt_and_taken() {
+        let mut player_moves = empty_player_moves("Fox");
+        player_moves.push(PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() });
+        
+        // P2 climbs 0 -> 10 -> 25 from P1's hits, then loses a stock and respawns at 0
+        let frames = [0.0, 10.0, 10.0, 25.0, 0.0].iter().map(|&percent| hit_frame(0, percent)).collect::<Vec<_>>();
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        
+        assert_eq!(player_moves[0].damage_dealt, 25.0);
+        assert_eq!(player_moves[0].damage_taken, 0.0);
+        assert_eq!(player_moves[1].damage_dealt, 0.0);
+        assert_eq!(player_moves[1].damage_taken, 25.0);
+    }
+
+    #[test]
+    fn test_openings_won() {
+        let mut player_moves = empty_player_moves("Fox");
+        player_moves.push(PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() });
+        
+        // A hit from neutral, then a follow-up while P2 is still in hitstun
+        let combo = || vec![
+            hit_frame(0, 0.0),
+            hit_frame(action_states::DAMAGE_HI_1, 12.0),
+            hit_frame(action_states::DAMAGE_HI_1, 12.0),
+            hit_frame(action_states::DAMAGE_HI_1, 20.0),
+        ];
+        analyze_frames(combo().into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].openings_won, 1);
+        
+        // Once P2 has been back in neutral long enough, the next hit is a new opening
+        let mut frames = combo();
+        frames.extend((0..OPENING_RESET_FRAMES).map(|_| hit_frame(0, 20.0)));
+        frames.push(hit_frame(action_states::DAMAGE_HI_1, 30.0));
+        let mut player_moves = empty_player_moves("Fox");
+        player_moves.push(PlayerMoveData { port: 1, character: "Marth".to_string(), ..Default::default() });
+        analyze_frames(frames.into_iter(), &mut player_moves, TEST_VERSION, None, IcsMode::Merge, OPENING_RESET_FRAMES);
+        assert_eq!(player_moves[0].openings_won, 2);
+    }
+
+    #[test]
+    fn test_game_start_time() {
+        let metadata = serde_json::json!({
+            "startAt": "2025-06-14T20:15:00.000Z",
+            "lastFrame": 8000,
+            "playedOn": "dolphin",
+        });
+        let start_time = game_start_time(metadata.as_object(), Some(Path::new("replays/Game_20250101T000000.slp")));
+        assert_eq!(start_time.as_deref(), Some("2025-06-14T20:15:00Z"));
+        
+        // Without metadata, the file name is used
+        let start_time = game_start_time(None, Some(Path::new("replays/Game_20250101T093000.slp")));
+        assert_eq!(start_time.as_deref(), Some("2025-01-01T09:30:00Z"));
+        assert_eq!(game_start_time(None, Some(Path::new("replays/match.slp"))), None);
+    }
+
+    #[test]
+    fn test_format_diff_text() {
+        let diff = StatsDiff {
+            total_games: 2,
+            players: vec![slippi_parser_service::PlayerDiff {
+                player: "MANG#0".to_string(),
+                moves: BTreeMap::from([("nair".to_string(), 45), ("shine".to_string(), -3)]),
+                metrics: BTreeMap::from([("apm".to_string(), -9.5), ("l_cancel_success_rate".to_string(), 0.032)]),
+            }],
+            only_before: vec!["ZAIN#0".to_string()],
+            only_after: vec![],
+        };
+        assert_eq!(
+            format_diff_text(&diff),
+            "Games: +2\nMANG#0\n  nair: +45\n  shine: -3\n  apm: -9.5\n  l_cancel_success_rate: +3.2%\nOnly in before: ZAIN#0\n"
+        );
     }
 }
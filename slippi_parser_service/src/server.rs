@@ -0,0 +1,133 @@
+// HTTP API serving parsed stats, for web dashboards
+use anyhow::Result;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::{open_replay, parse_slippi_file, process_directory_for_moves, AnalysisConfig, GameData, MoveStats};
+
+type ApiError = (StatusCode, String);
+
+// Requests may only read below this directory
+struct ServerState {
+    root: PathBuf,
+}
+
+#[derive(serde::Deserialize)]
+struct StatsQuery {
+    dir: PathBuf,
+    #[serde(default)]
+    recursive: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct GameQuery {
+    file: PathBuf,
+    #[serde(default)]
+    extract_moves: bool,
+}
+
+pub fn router(root: &Path) -> Result<Router> {
+    let state = ServerState { root: root.canonicalize()? };
+    
+    Ok(Router::new()
+        .route("/stats", get(stats))
+        .route("/game", get(game))
+        .with_state(Arc::new(state)))
+}
+
+pub async fn serve(root: &Path, addr: &str) -> Result<()> {
+    let app = router(root)?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Serving {:?} on http://{}", root, listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+// GET /stats?dir=<path>: aggregated move statistics for a directory of JSON files
+async fn stats(State(state): State<Arc<ServerState>>, Query(query): Query<StatsQuery>) -> Result<Json<MoveStats>, ApiError> {
+    let directory = state.resolve(&query.dir)?;
+    process_directory_for_moves(&directory, &AnalysisConfig::builder().recursive(query.recursive).build())
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))
+}
+
+// GET /game?file=<path>: one parsed replay
+async fn game(State(state): State<Arc<ServerState>>, Query(query): Query<GameQuery>) -> Result<Json<GameData>, ApiError> {
+    let file = state.resolve(&query.file)?;
+    let reader = open_replay(&file).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    parse_slippi_file(reader, Some(&file), &AnalysisConfig::builder().extract_moves(query.extract_moves).build())
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))
+}
+
+impl ServerState {
+    // Resolve a requested path against the root, rejecting anything that
+    // escapes it (via "..", an absolute path or a symlink)
+    fn resolve(&self, requested: &Path) -> Result<PathBuf, ApiError> {
+        let path = self.root
+            .join(requested)
+            .canonicalize()
+            .map_err(|_| (StatusCode::NOT_FOUND, format!("{} not found", requested.display())))?;
+        
+        if !path.starts_with(&self.root) {
+            warn!("Rejected request for {:?} outside {:?}", requested, self.root);
+            return Err((StatusCode::FORBIDDEN, format!("{} is outside the served directory", requested.display())));
+        }
+        
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+    
+    async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_stats_endpoint() {
+        let root = tempfile::tempdir().unwrap();
+        let games = root.path().join("parsedgames");
+        std::fs::create_dir(&games).unwrap();
+        let game = serde_json::json!({
+            "schema_version": crate::SCHEMA_VERSION,
+            "player_count": 1,
+            "duration_frames": 1000,
+            "stage": "Battlefield",
+            "start_time": null,
+            "players": [],
+            "winner": null,
+            "winning_team": null,
+            "moves": [{"port": 1, "character": "Fox", "display_name": null, "connect_code": null, "team": null, "moves": {"shine": 12}}]
+        });
+        std::fs::write(games.join("Game_1.json"), game.to_string()).unwrap();
+        std::fs::write(games.join("Game_2.json"), game.to_string()).unwrap();
+        
+        let app = router(root.path()).unwrap();
+        let (status, stats) = get_json(app.clone(), "/stats?dir=parsedgames").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(stats["total_games"], 2);
+        assert_eq!(stats["players"][0]["moves"]["shine"], 12);
+        
+        // Paths outside the root are rejected, missing ones are not found
+        let (status, _) = get_json(app.clone(), "/stats?dir=..").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = get_json(app, "/game?file=missing.slp").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,207 @@
+//! Stage names, and geometry for the tournament-legal stages in game units.
+//!
+//! Every legal stage is symmetric, so the ledges sit at `-ledge_x` and
+//! `ledge_x` and the main platform's surface is at y = 0.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StageBounds {
+    pub ledge_x: f32,
+    pub blast_left: f32,
+    pub blast_right: f32,
+    pub blast_top: f32,
+    pub blast_bottom: f32,
+    pub platforms: &'static [Platform],
+}
+
+// A pass-through platform: its surface height and left and right edges
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Platform {
+    pub height: f32,
+    pub left: f32,
+    pub right: f32,
+}
+
+// How far above or below a platform's surface still counts as standing on it
+const PLATFORM_TOLERANCE: f32 = 1.0;
+
+impl StageBounds {
+    // Past either ledge, i.e. off the main platform
+    pub fn is_offstage(&self, x: f32) -> bool {
+        x.abs() > self.ledge_x
+    }
+    
+    // The platform a position is standing on, if any
+    pub fn platform_at(&self, x: f32, y: f32) -> Option<&Platform> {
+        self.platforms.iter().find(|p| (y - p.height).abs() <= PLATFORM_TOLERANCE && (p.left..=p.right).contains(&x))
+    }
+}
+
+const fn side_platforms(height: f32, inner: f32, outer: f32) -> [Platform; 2] {
+    [Platform { height, left: -outer, right: -inner }, Platform { height, left: inner, right: outer }]
+}
+
+const fn top_platform(height: f32, half_width: f32) -> Platform {
+    Platform { height, left: -half_width, right: half_width }
+}
+
+const BATTLEFIELD_PLATFORMS: [Platform; 3] = {
+    let [left, right] = side_platforms(27.2, 20.0, 57.6);
+    [left, right, top_platform(54.4, 18.8)]
+};
+const YOSHIS_STORY_PLATFORMS: [Platform; 3] = {
+    let [left, right] = side_platforms(23.45, 28.0, 59.5);
+    [left, right, top_platform(42.0, 15.75)]
+};
+const DREAM_LAND_PLATFORMS: [Platform; 3] = {
+    let [left, right] = side_platforms(30.24, 31.7, 61.4);
+    [left, right, top_platform(51.43, 19.02)]
+};
+const POKEMON_STADIUM_PLATFORMS: [Platform; 2] = side_platforms(25.0, 25.0, 55.0);
+// Fountain of Dreams' side platforms move up and down, so only the top one is listed
+const FOUNTAIN_OF_DREAMS_PLATFORMS: [Platform; 1] = [top_platform(42.75, 14.25)];
+
+// Map a stage ID to its canonical name
+pub fn stage_name(stage_id: u16) -> String {
+    let name = match stage_id {
+        2 => "Fountain of Dreams",
+        3 => "Pokémon Stadium",
+        4 => "Princess Peach's Castle",
+        5 => "Kongo Jungle",
+        6 => "Brinstar",
+        7 => "Corneria",
+        8 => "Yoshi's Story",
+        9 => "Onett",
+        10 => "Mute City",
+        11 => "Rainbow Cruise",
+        12 => "Jungle Japes",
+        13 => "Great Bay",
+        14 => "Hyrule Temple",
+        15 => "Brinstar Depths",
+        16 => "Yoshi's Island",
+        17 => "Green Greens",
+        18 => "Fourside",
+        19 => "Mushroom Kingdom I",
+        20 => "Mushroom Kingdom II",
+        22 => "Venom",
+        23 => "Poké Floats",
+        24 => "Big Blue",
+        25 => "Icicle Mountain",
+        26 => "Icetop",
+        27 => "Flat Zone",
+        28 => "Dream Land N64",
+        29 => "Yoshi's Island N64",
+        30 => "Kongo Jungle N64",
+        31 => "Battlefield",
+        32 => "Final Destination",
+        _ => return format!("Unknown({})", stage_id),
+    };
+    
+    name.to_string()
+}
+
+// Bounds for a stage ID from the game start block, or None for stages
+// without a table entry (everything not tournament-legal)
+pub fn stage_bounds(stage_id: u16) -> Option<StageBounds> {
+    let (ledge_x, blast_x, blast_top, blast_bottom, platforms): (f32, f32, f32, f32, &'static [Platform]) = match stage_id {
+        // Fountain of Dreams
+        2 => (63.35, 198.75, 202.5, -146.25, &FOUNTAIN_OF_DREAMS_PLATFORMS),
+        // Pokémon Stadium
+        3 => (87.75, 230.0, 180.0, -111.0, &POKEMON_STADIUM_PLATFORMS),
+        // Yoshi's Story
+        8 => (56.0, 175.7, 168.0, -91.0, &YOSHIS_STORY_PLATFORMS),
+        // Dream Land N64
+        28 => (77.27, 255.0, 250.0, -123.0, &DREAM_LAND_PLATFORMS),
+        // Battlefield
+        31 => (68.4, 224.0, 200.0, -108.8, &BATTLEFIELD_PLATFORMS),
+        // Final Destination
+        32 => (85.5657, 246.0, 188.0, -140.0, &[]),
+        _ => return None,
+    };
+    
+    Some(StageBounds {
+        ledge_x,
+        blast_left: -blast_x,
+        blast_right: blast_x,
+        blast_top,
+        blast_bottom,
+        platforms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGAL_STAGES: [u16; 6] = [2, 3, 8, 28, 31, 32];
+
+    #[test]
+    fn test_stage_name() {
+        assert_eq!(stage_name(31), "Battlefield");
+        assert_eq!(stage_name(32), "Final Destination");
+        assert_eq!(stage_name(3), "Pokémon Stadium");
+        assert_eq!(stage_name(28), "Dream Land N64");
+        assert_eq!(stage_name(21), "Unknown(21)");
+        assert_eq!(stage_name(500), "Unknown(500)");
+    }
+
+    #[test]
+    fn test_battlefield_bounds() {
+        let battlefield = stage_bounds(31).unwrap();
+        assert_eq!(battlefield.ledge_x, 68.4);
+        assert_eq!((battlefield.blast_left, battlefield.blast_right), (-224.0, 224.0));
+        assert_eq!((battlefield.blast_top, battlefield.blast_bottom), (200.0, -108.8));
+        assert_eq!(battlefield.platforms.len(), 3);
+        
+        // Side platforms, then the top one
+        assert_eq!(battlefield.platform_at(-40.0, 27.2), Some(&Platform { height: 27.2, left: -57.6, right: -20.0 }));
+        assert_eq!(battlefield.platform_at(0.0, 54.4).map(|p| p.height), Some(54.4));
+        assert_eq!(battlefield.platform_at(0.0, 27.2), None);
+        assert_eq!(battlefield.platform_at(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_final_destination_bounds() {
+        let final_destination = stage_bounds(32).unwrap();
+        assert_eq!(final_destination.ledge_x, 85.5657);
+        assert_eq!((final_destination.blast_left, final_destination.blast_right), (-246.0, 246.0));
+        assert_eq!((final_destination.blast_top, final_destination.blast_bottom), (188.0, -140.0));
+        assert!(final_destination.platforms.is_empty());
+        assert!(!final_destination.is_offstage(-85.0));
+        assert!(final_destination.is_offstage(-86.0));
+    }
+
+    #[test]
+    fn test_yoshis_story_bounds() {
+        let yoshis_story = stage_bounds(8).unwrap();
+        assert_eq!(yoshis_story.ledge_x, 56.0);
+        assert_eq!((yoshis_story.blast_left, yoshis_story.blast_right), (-175.7, 175.7));
+        assert_eq!((yoshis_story.blast_top, yoshis_story.blast_bottom), (168.0, -91.0));
+        assert_eq!(yoshis_story.platform_at(40.0, 23.45).map(|p| (p.left, p.right)), Some((28.0, 59.5)));
+        assert_eq!(yoshis_story.platform_at(0.0, 42.0).map(|p| p.height), Some(42.0));
+        // Offstage from just past the ledge, on either side
+        assert!(!yoshis_story.is_offstage(56.0));
+        assert!(yoshis_story.is_offstage(56.5));
+        assert!(yoshis_story.is_offstage(-56.5));
+    }
+
+    #[test]
+    fn test_legal_stages_are_consistent() {
+        for stage_id in LEGAL_STAGES {
+            let bounds = stage_bounds(stage_id).unwrap();
+            assert_eq!(bounds.blast_left, -bounds.blast_right, "stage {}", stage_id);
+            assert!(bounds.ledge_x < bounds.blast_right, "stage {}", stage_id);
+            assert!(bounds.blast_bottom < 0.0 && bounds.blast_top > 0.0, "stage {}", stage_id);
+            for platform in bounds.platforms {
+                assert!(platform.left < platform.right, "stage {}", stage_id);
+                assert!(platform.height > 0.0 && platform.height < bounds.blast_top, "stage {}", stage_id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_stage_has_no_bounds() {
+        assert_eq!(stage_bounds(0), None);
+        // Yoshi's Island N64 is in the game but not tournament-legal
+        assert_eq!(stage_bounds(29), None);
+    }
+}
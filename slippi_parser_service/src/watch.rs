@@ -0,0 +1,161 @@
+// Watch a directory and parse replays as they are written, for live sessions
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::{open_replay, parse_slippi_file, AnalysisConfig, GameData};
+
+// How often pending replays are checked while no file events arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Parse attempts on a replay that stopped changing before it's given up on
+const MAX_ATTEMPTS: u32 = 5;
+
+struct Pending {
+    last_change: Instant,
+    attempts: u32,
+}
+
+// Replays waiting to be parsed. A replay is due once it hasn't changed for
+// `settle`; Slippi writes replays incrementally, so a file that still fails to
+// parse is put back to wait for another quiet period.
+struct PendingReplays {
+    settle: Duration,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+impl PendingReplays {
+    fn new(settle: Duration) -> Self {
+        PendingReplays { settle, pending: HashMap::new() }
+    }
+    
+    // The file was created or written to
+    fn touched(&mut self, path: PathBuf, now: Instant) {
+        self.pending.entry(path).or_insert(Pending { last_change: now, attempts: 0 }).last_change = now;
+    }
+    
+    // Replays that have been quiet long enough, in path order
+    fn due(&self, now: Instant) -> Vec<PathBuf> {
+        let mut due: Vec<PathBuf> = self.pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.last_change) >= self.settle)
+            .map(|(path, _)| path.clone())
+            .collect();
+        due.sort();
+        due
+    }
+    
+    // Parse every due replay, returning the ones that parsed
+    async fn parse_due(&mut self, now: Instant, extract_moves: bool) -> Vec<(PathBuf, GameData)> {
+        let config = AnalysisConfig::builder().extract_moves(extract_moves).build();
+        let mut parsed = Vec::new();
+        for path in self.due(now) {
+            let result = match open_replay(&path) {
+                Ok(reader) => parse_slippi_file(reader, Some(&path), &config).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(game_data) => {
+                    self.pending.remove(&path);
+                    parsed.push((path, game_data));
+                }
+                Err(e) => {
+                    let Some(pending) = self.pending.get_mut(&path) else { continue };
+                    pending.attempts += 1;
+                    if pending.attempts >= MAX_ATTEMPTS {
+                        warn!("Giving up on {:?} after {} attempts: {}", path, pending.attempts, e);
+                        self.pending.remove(&path);
+                    } else {
+                        info!("{:?} isn't complete yet, retrying: {}", path, e);
+                        pending.last_change = now;
+                    }
+                }
+            }
+        }
+        parsed
+    }
+}
+
+fn is_replay(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        name.ends_with(".slp") || name.ends_with(".slp.gz")
+    })
+}
+
+// Print one line of GameData JSON to stdout for every replay written to the
+// directory, until the watcher stops
+pub async fn watch(directory: &Path, recursive: bool, extract_moves: bool, settle: Duration) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(directory, mode)?;
+    info!("Watching {:?} for new replays", directory);
+    
+    let mut pending = PendingReplays::new(settle);
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths.into_iter().filter(|path| is_replay(path)) {
+                        pending.touched(path, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(e)) => warn!("Watch error: {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        
+        for (path, game_data) in pending.parse_due(Instant::now(), extract_moves).await {
+            info!("Parsed {:?}", path);
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer(&mut stdout, &game_data)?;
+            stdout.write_all(b"\n")?;
+            stdout.flush()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_replay_bytes;
+    
+    #[tokio::test]
+    async fn test_partial_write_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Game_1.slp");
+        let replay = test_replay_bytes();
+        let settle = Duration::from_secs(2);
+        let mut pending = PendingReplays::new(settle);
+        
+        // Half the replay has been written
+        let start = Instant::now();
+        std::fs::write(&path, &replay[..replay.len() / 2]).unwrap();
+        pending.touched(path.clone(), start);
+        assert!(pending.parse_due(start + Duration::from_millis(500), false).await.is_empty());
+        
+        // It stopped changing but doesn't parse yet, so it waits for another quiet period
+        let quiet = start + settle;
+        assert!(pending.parse_due(quiet, false).await.is_empty());
+        assert!(pending.due(quiet + Duration::from_millis(500)).is_empty());
+        
+        // The rest arrives
+        let finished = quiet + Duration::from_secs(1);
+        std::fs::write(&path, &replay).unwrap();
+        pending.touched(path.clone(), finished);
+        let parsed = pending.parse_due(finished + settle, false).await;
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].1.stage, "Battlefield");
+        assert!(pending.pending.is_empty());
+        
+        assert!(is_replay(Path::new("replays/Game_2.slp.gz")));
+        assert!(!is_replay(Path::new("replays/Game_2.json")));
+    }
+}
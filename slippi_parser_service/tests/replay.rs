@@ -0,0 +1,236 @@
+// End-to-end tests against a replay fixture on disk, running the built parser
+// binary so the whole peppi read path is exercised rather than hand-built frames.
+//
+// tests/fixtures/marth_vs_ganondorf_fd.slp is a real Dolphin recording: a
+// seven-second Marth (port 1) vs Ganondorf (port 2) game on Final Destination,
+// taken from peppi's own test data (shield_drop.slp, MIT licensed). Marth
+// dash dances 14 times and Ganondorf jabs once. `test_synthetic_replay` additionally
+// writes a game with peppi, where every frame is known, and checks that.
+
+use peppi::frame::{mutable, PortOccupancy};
+use peppi::game::immutable::Game;
+use peppi::game::{Bytes, End, EndMethod, Player, PlayerType, Port, Quirks, Start, Ucf};
+use peppi::io::slippi::{Slippi, Version};
+use slippi_parser_service::analysis::{extract_moves_from_frames, iter_moves};
+use slippi_parser_service::{action_states, process_directory_for_moves, AnalysisConfig, FrameRange, GameData, IcsMode, COUNTDOWN_FRAMES, OPENING_RESET_FRAMES};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/marth_vs_ganondorf_fd.slp")
+}
+
+#[test]
+fn test_parse_fixture_replay() {
+    let output = Command::new(env!("CARGO_BIN_EXE_slippi_parser_service"))
+        .arg("-f").arg(fixture_path())
+        .args(["--extract-moves", "--no-cache", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    
+    let game: GameData = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(game.player_count, 2);
+    assert_eq!(game.stage, "Final Destination");
+    assert_eq!(game.players.iter().map(|p| p.character.as_str()).collect::<Vec<_>>(), vec!["Marth", "Ganondorf"]);
+    
+    let moves = game.moves.unwrap();
+    assert_eq!(moves[0].moves.get("dash_dance"), Some(&14));
+    assert_eq!(moves[1].moves.get("jab"), Some(&1));
+}
+
+#[test]
+fn test_port_narrows_single_replay() {
+    let parse = |port: &str| Command::new(env!("CARGO_BIN_EXE_slippi_parser_service"))
+        .arg("-f").arg(fixture_path())
+        .args(["--extract-moves", "--no-cache", "--quiet", "--port", port])
+        .output()
+        .unwrap();
+    
+    // Ports are 0-based, so 1 is Ganondorf on P2
+    let output = parse("1");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let game: GameData = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(game.players.iter().map(|p| p.character.as_str()).collect::<Vec<_>>(), vec!["Ganondorf"]);
+    assert_eq!(game.moves.unwrap().iter().map(|p| p.port).collect::<Vec<_>>(), vec![1]);
+    
+    // Nobody is on P4
+    assert!(!parse("3").status.success());
+}
+
+// Files the parser writes are what the directory analyzer reads back, so a
+// field that doesn't survive the trip breaks the two-stage pipeline
+#[tokio::test]
+async fn test_parsed_json_round_trips_through_directory_stats() {
+    let dir = tempfile::tempdir().unwrap();
+    let replays = dir.path().join("replays");
+    let parsed = dir.path().join("parsed");
+    std::fs::create_dir(&replays).unwrap();
+    std::fs::copy(fixture_path(), replays.join("Game_20250614T201500.slp")).unwrap();
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_slippi_parser_service"))
+        .arg("-f").arg(&replays)
+        .arg("--output-dir").arg(&parsed)
+        .args(["--extract-moves", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    
+    let json = std::fs::read_to_string(parsed.join("Game_20250614T201500.json")).unwrap();
+    let game: GameData = serde_json::from_str(&json).unwrap();
+    // Nothing written is lost or renamed when read back
+    assert_eq!(json_value(&game), serde_json::from_str::<serde_json::Value>(&json).unwrap());
+    
+    let stats = process_directory_for_moves(&parsed, &AnalysisConfig::default()).await.unwrap();
+    assert_eq!(stats.total_games, 1);
+    assert!(stats.failed_files.is_empty());
+    // Each player's entry comes through as the parser wrote it
+    let moves = game.moves.unwrap();
+    assert_eq!(json_value(&stats.players), json_value(&moves));
+    
+    let total_moves: u32 = moves.iter().flat_map(|p| p.moves.values()).sum();
+    assert!(total_moves > 0);
+    assert_eq!(stats.ranked_moves.iter().map(|(_, count)| count).sum::<u32>(), total_moves);
+}
+
+// Compare as written: going through a string keeps f32s at their printed
+// precision, where serde_json::to_value would widen them to f64
+fn json_value<T: serde::Serialize>(value: &T) -> serde_json::Value {
+    serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+}
+
+#[test]
+fn test_iter_moves_on_fixture() {
+    let game = peppi::io::slippi::read(&mut File::open(fixture_path()).unwrap(), None).unwrap();
+    let version = game.start.slippi.version;
+    let moves: Vec<_> = iter_moves(&game, version).map(|e| (e.port, e.move_name)).collect();
+    // Pattern-detected techniques are yielded alongside action-state moves
+    assert_eq!(moves.iter().filter(|(port, name)| *port == 0 && name == "dash_dance").count(), 14);
+    assert_eq!(moves.iter().filter(|(port, _)| *port == 1).collect::<Vec<_>>(), vec![&(1, "jab".to_string())]);
+    
+    // Every counted move is yielded once
+    let (player_moves, _) = extract_moves_from_frames(&game.frames, &game.start.players, version, game.start.stage, IcsMode::Merge, FrameRange::default(), OPENING_RESET_FRAMES);
+    let mut tallies: HashMap<u8, HashMap<String, u32>> = HashMap::new();
+    for (port, move_name) in moves {
+        *tallies.entry(port).or_default().entry(move_name).or_insert(0) += 1;
+    }
+    for player in player_moves {
+        assert_eq!(tallies.remove(&player.port).unwrap_or_default(), player.moves);
+    }
+}
+
+// A two-second Fox (port 1) vs Falco (port 2) game on Battlefield, written with
+// peppi so the exact moves are known and the writer round-trips through the parser
+#[test]
+fn test_synthetic_replay() {
+    let version = Version(2, 0, 0);
+    let frame_count = COUNTDOWN_FRAMES as usize + 120;
+    
+    // Raw start block, with every port marked empty (player type 3) until written
+    let mut start_bytes = vec![0; 0x1A2];
+    for port in 0..4 {
+        start_bytes[0x65 + 0x24 * port] = 3;
+    }
+    let player = |port, character| Player {
+        port,
+        character,
+        r#type: PlayerType::Human,
+        stocks: 4,
+        costume: 0,
+        team: None,
+        handicap: 9,
+        bitfield: 192,
+        cpu_level: None,
+        damage_start: 0,
+        damage_spawn: 0,
+        offense_ratio: 1.0,
+        defense_ratio: 1.0,
+        model_scale: 1.0,
+        ucf: Some(Ucf { dash_back: None, shield_drop: None }),
+        name_tag: Some(peppi::game::shift_jis::MeleeString(String::new())),
+        netplay: None,
+    };
+    
+    let ports = [Port::P1, Port::P2].map(|port| PortOccupancy { port, follower: false });
+    let mut frames = mutable::Frame::with_capacity(frame_count, version, &ports);
+    for frame_idx in 0..frame_count {
+        frames.id.push(Some(peppi::frame::FIRST_INDEX + frame_idx as i32));
+        for port_data in &mut frames.ports {
+            port_data.leader.push_null(version);
+        }
+    }
+    // Fox fairs a second after GO!, Falco nairs half a second later; both
+    // stand still otherwise
+    let go = COUNTDOWN_FRAMES as usize;
+    for port_data in &mut frames.ports {
+        port_data.leader.validity = None;
+        for frame_idx in 0..frame_count {
+            port_data.leader.pre.state.set(frame_idx, Some(action_states::WAIT));
+            port_data.leader.post.state.set(frame_idx, Some(action_states::WAIT));
+            port_data.leader.post.stocks.set(frame_idx, Some(4));
+        }
+    }
+    for frame_idx in go + 60..go + 70 {
+        frames.ports[0].leader.pre.state.set(frame_idx, Some(action_states::ATTACK_AIR_F));
+    }
+    for frame_idx in go + 90..go + 100 {
+        frames.ports[1].leader.pre.state.set(frame_idx, Some(action_states::ATTACK_AIR_N));
+    }
+    
+    let game = Game {
+        start: Start {
+            slippi: Slippi { version },
+            bitfield: [0; 4],
+            is_raining_bombs: false,
+            is_teams: false,
+            item_spawn_frequency: -1,
+            self_destruct_score: -1,
+            stage: 31,
+            timer: 480,
+            item_spawn_bitfield: [0; 5],
+            damage_ratio: 1.0,
+            players: vec![player(Port::P1, 2), player(Port::P2, 20)],
+            random_seed: 0,
+            bytes: Bytes(start_bytes),
+            is_pal: Some(false),
+            is_frozen_ps: Some(false),
+            scene: None,
+            language: None,
+            r#match: None,
+        },
+        end: Some(End {
+            method: EndMethod::Game,
+            bytes: Bytes(vec![0; 2]),
+            lras_initiator: Some(None),
+            players: None,
+        }),
+        frames: frames.into(),
+        metadata: None,
+        gecko_codes: None,
+        hash: None,
+        quirks: Some(Quirks { double_game_end: false }),
+    };
+    
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fox_vs_falco_battlefield.slp");
+    peppi::io::slippi::write(&mut File::create(&path).unwrap(), &game).unwrap();
+    
+    let read_back = peppi::io::slippi::read(&mut File::open(&path).unwrap(), None).unwrap();
+    let moves: Vec<_> = iter_moves(&read_back, version).map(|e| (e.port, e.move_name)).collect();
+    assert_eq!(moves, vec![(0, "fair".to_string()), (1, "nair".to_string())]);
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_slippi_parser_service"))
+        .arg("-f").arg(&path)
+        .args(["--extract-moves", "--no-cache", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let parsed: GameData = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed.stage, "Battlefield");
+    assert_eq!(parsed.players.iter().map(|p| p.character.as_str()).collect::<Vec<_>>(), vec!["Fox", "Falco"]);
+    let parsed_moves = parsed.moves.unwrap();
+    assert_eq!(parsed_moves[0].moves.get("fair"), Some(&1));
+    assert_eq!(parsed_moves[1].moves.get("nair"), Some(&1));
+}